@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::collections::{BinaryHeap, HashMap};
 
 use futures::stream::FuturesUnordered;
@@ -7,12 +7,17 @@ use futures::{Future, FutureExt, StreamExt};
 use tokio::sync::mpsc;
 
 use lazy_static::lazy_static;
+use tracing::{debug, debug_span, Instrument};
 
 use crate::MessageForAccounting;
 use crate::http::client::Client;
 use crate::http::dto::License;
+use crate::http::error::{DescriptiveError, ErrorKind};
+use crate::http::scheduler::Endpoint;
 use crate::models::data::Treasure;
+use crate::models::priority::Prioritized;
 use crate::actors::Actor;
+use crate::strategy::SharedStrategy;
 
 // const COINS_MAX: usize = 21;
 
@@ -27,23 +32,50 @@ lazy_static! {
     ].into_iter().collect();
 }
 
+/// Endpoints whose circuit is open are left alone until it may close again.
+#[derive(Default)]
+struct Backoff {
+    until: HashMap<Endpoint, Instant>,
+}
+
+impl Backoff {
+    fn observe(&mut self, endpoint: Endpoint, e: &DescriptiveError) {
+        if let ErrorKind::CircuitOpen { retry_in } = e.kind {
+            self.until.insert(endpoint, Instant::now() + retry_in);
+        }
+    }
+
+    fn ready(&self, endpoint: Endpoint) -> bool {
+        self.until
+            .get(&endpoint)
+            .is_none_or(|until| *until <= Instant::now())
+    }
+}
+
 pub struct Accounting {
     client: Client,
+    strategy: SharedStrategy,
     rx: mpsc::Receiver<MessageForAccounting>,
-    treasures: BinaryHeap<Treasure>,
+    treasures: BinaryHeap<Prioritized<Treasure>>,
     // coins_to_use: usize,
     digs_pending: u64,
     active_licenses: u8,
     licenses: Vec<License>,
     coins: Vec<u64>,
     max_concurrent_licenses: u8,
+    backoff: Backoff,
 }
 
 impl Accounting {
-    pub fn new(c: &Client, max_concurrent_licenses: u8) -> impl FnOnce(mpsc::Receiver<MessageForAccounting>) -> Self {
+    pub fn new(
+        c: &Client,
+        strategy: SharedStrategy,
+        max_concurrent_licenses: u8,
+    ) -> impl FnOnce(mpsc::Receiver<MessageForAccounting>) -> Self {
         let client = c.clone();
         move |rx| Self {
             client,
+            strategy,
             rx,
             treasures: BinaryHeap::new(),
             // coins_to_use: 2,
@@ -52,6 +84,7 @@ impl Accounting {
             licenses: vec![],
             coins: vec![],
             max_concurrent_licenses,
+            backoff: Backoff::default(),
         }
     }
 }
@@ -59,29 +92,45 @@ impl Accounting {
 impl Accounting {
     fn claim_treasures(
         client: &Client,
-        treasures: &mut BinaryHeap<Treasure>,
-    ) -> FuturesUnordered<impl Future<Output = Result<Vec<u64>, Treasure>>> {
+        treasures: &mut BinaryHeap<Prioritized<Treasure>>,
+    ) -> FuturesUnordered<impl Future<Output = Result<Vec<u64>, (Treasure, DescriptiveError)>>> {
         treasures
             .drain()
+            .map(Prioritized::into_inner)
             .map(|t| {
                 let cl = client.clone();
-                async move { (cl.cash(&t).await, t) }
+                let span = debug_span!("cash", trace = t.trace, depth = t.depth);
+                async move {
+                    let res = cl.cash(&t).await;
+                    if let Ok(coins) = &res {
+                        debug!(coins = coins.len(), "cashed");
+                    }
+                    (res, t)
+                }
+                .instrument(span)
             })
             .map(|future|
-                future.map(|(res, t)| res.map_err(|_| t))
+                future.map(|(res, t)| res.map_err(|e| (t, e)))
             )
             .collect()
     }
 
-    async fn claim_all(client: &Client, treasures: &mut BinaryHeap<Treasure>) -> Vec<u64> {
+    async fn claim_all(
+        client: &Client,
+        treasures: &mut BinaryHeap<Prioritized<Treasure>>,
+        backoff: &mut Backoff,
+    ) -> Vec<u64> {
         let results = Accounting::claim_treasures(client, treasures)
-            .collect::<Vec<Result<Vec<u64>, Treasure>>>()
+            .collect::<Vec<Result<Vec<u64>, (Treasure, DescriptiveError)>>>()
             .await;
 
         results.into_iter().fold(vec![], |mut coins, r| {
             match r {
                 Ok(c) => coins.extend(c),
-                Err(t) => treasures.push(t),
+                Err((t, e)) => {
+                    backoff.observe(Endpoint::Cash, &e);
+                    treasures.push(Prioritized::new(t, Treasure::priority))
+                }
             };
             coins
         })
@@ -89,51 +138,70 @@ impl Accounting {
 
     fn fetch_licenses(
         client: &Client,
+        strategy: &SharedStrategy,
         amount: u8,
         coins: &mut Vec<u64>
-    ) -> FuturesUnordered<impl Future<Output=Result<License, Vec<u64>>>> {
+    ) -> FuturesUnordered<impl Future<Output=Result<License, (Vec<u64>, DescriptiveError)>>> {
         (0..amount)
             .map(|_| {
                 let cl = client.clone();
-                let coin = if let Some(c) = coins.pop() {
-                    vec![c]
-                } else {
-                    vec![]
-                };
+                let coin = strategy.coins_for_license(coins);
                 async move { (cl.get_license(&coin).await, coin) }
             })
             .map(|future|
-                future.map(|(res, coin)| res.map_err(|_| coin))
+                future.map(|(res, coin)| res.map_err(|e| (coin, e)))
             )
             .collect()
     }
 
-    async fn fetch_and_update(client: &Client, amount: u8, coins: &mut Vec<u64>) -> Vec<License> {
-        let licenses = Accounting::fetch_licenses(client, amount, coins)
-            .collect::<Vec<Result<License, Vec<u64>>>>()
+    async fn fetch_and_update(
+        client: &Client,
+        strategy: &SharedStrategy,
+        amount: u8,
+        coins: &mut Vec<u64>,
+        backoff: &mut Backoff,
+    ) -> Vec<License> {
+        let licenses = Accounting::fetch_licenses(client, strategy, amount, coins)
+            .collect::<Vec<Result<License, (Vec<u64>, DescriptiveError)>>>()
             .await;
 
         licenses.into_iter().fold(vec![], |mut acc, item| {
             match item {
                 Ok(lic) => acc.push(lic),
-                Err(c) => coins.extend(c),
+                Err((c, e)) => {
+                    backoff.observe(Endpoint::License, &e);
+                    // a license given up on may have been paid for, its coins are not used again
+                    if e.kind != ErrorKind::Timeout {
+                        coins.extend(c)
+                    }
+                }
             };
             acc
         })
     }
 
     async fn cash_out(&mut self) {
+        if !self.backoff.ready(Endpoint::Cash) {
+            return;
+        }
         self.coins.extend(
-            Accounting::claim_all(&self.client, &mut self.treasures).await);
+            Accounting::claim_all(&self.client, &mut self.treasures, &mut self.backoff).await);
     }
 
     async fn prep_licenses(&mut self) {
-        let to_prep = self.max_concurrent_licenses - self.active_licenses;
+        if !self.backoff.ready(Endpoint::License) {
+            return;
+        }
+        let to_prep = self
+            .strategy
+            .licenses_to_prep(self.active_licenses, self.max_concurrent_licenses);
         if to_prep > 0 {
             let licenses = Accounting::fetch_and_update(
                 &self.client,
+                &self.strategy,
                 to_prep,
-                &mut self.coins
+                &mut self.coins,
+                &mut self.backoff,
             ).await;
             self.active_licenses += licenses.len() as u8;
             self.licenses.extend(licenses);
@@ -147,9 +215,12 @@ impl Accounting {
             match tokio::time::timeout(Duration::from_millis(9), self.rx.recv()).await {
                 Ok(Some(message)) => match message {
                     MessageForAccounting::TreasureToClaim(tid) => {
-                        let depth = tid.depth;
+                        let (depth, trace) = (tid.depth, tid.trace);
                         tid.treasures.into_iter()
-                            .for_each(|t| self.treasures.push(Treasure::new(depth, t)));
+                            .for_each(|t| {
+                                self.treasures
+                                    .push(Prioritized::new(Treasure::new(depth, t, trace), Treasure::priority))
+                            });
                         self.cash_out().await;
                     }
                     MessageForAccounting::LicenseExpired(digs_pending) => {
@@ -167,6 +238,13 @@ impl Accounting {
                     self.prep_licenses().await;
                 },
             }
+            self.client
+                .metrics()
+                .record_balance(
+                    self.coins.len() as u64,
+                    self.active_licenses,
+                    self.treasures.len() as u64,
+                );
         }
     }
 }
@@ -1,9 +1,36 @@
+use crate::http::breaker::BreakerState;
+use crate::http::scheduler::Endpoint;
+use crate::models::cost::ExploreCostModel;
+use crate::models::heatmap::FieldMap;
+use crate::models::ledger::{DigOutcome, DigRecord};
 use crate::models::messages::StatsMessage;
+use crate::models::metrics::{EndpointStats, Origin, Registry, Snapshot};
+use crate::models::report::{CellsReport, Report};
+use crate::models::timeline::Timeline;
 use crate::actors::Actor;
-use histogram::Histogram;
 use reqwest::StatusCode;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Where reports go, nothing is written for a missing path.
+#[derive(Debug, Clone, Default)]
+pub struct StatsFiles {
+    pub report: Option<String>,
+    /// CSV when the name ends with `.csv`, JSON otherwise
+    pub timeline: Option<String>,
+    /// directory for field map images and CSVs, written at the end of the game
+    pub map_dir: Option<String>,
+}
+
+impl StatsFiles {
+    pub fn any(&self) -> bool {
+        self.report.is_some() || self.timeline.is_some()
+    }
+}
 
 pub struct StatsActor {
     stats: Stats,
@@ -11,9 +38,14 @@ pub struct StatsActor {
 }
 
 impl StatsActor {
-    pub fn new(rx: mpsc::Receiver<StatsMessage>) -> Self {
+    pub fn new(
+        rx: mpsc::Receiver<StatsMessage>,
+        registry: Arc<Registry>,
+        files: StatsFiles,
+        started: Instant,
+    ) -> Self {
         StatsActor {
-            stats: Stats::new(),
+            stats: Stats::new(registry, files, started),
             rx,
         }
     }
@@ -23,148 +55,111 @@ impl StatsActor {
         while let Some(msg) = self.rx.recv().await {
             match msg {
                 ShowStats => println!("{}", self.stats),
-                RecordExplore {
-                    area_size,
-                    duration,
-                    status,
-                } => self.stats.record_explore(area_size, duration, status),
-                RecordDig {
-                    depth,
-                    x,
-                    y,
-                    duration,
-                    found,
-                    status,
-                } => self.stats.record_dig(duration, x, y, depth, found, status),
-                RecordCash {
-                    duration,
-                    depth,
-                    amount,
-                    status,
-                } => self.stats.record_cash(duration, depth, amount, status),
-                RecordLicense {
-                    duration,
-                    coins,
-                    allowed,
-                    status,
-                } => self.stats.record_license(duration, coins, allowed, status),
+                ResetStats => self.stats.reset(),
+                RecordCell { origin, x, y, history } => {
+                    self.stats.record_cell(origin, x, y, history)
+                }
+                RecordArea { area, amount } => self.stats.map.record_area(area, amount),
+                Sample => self.stats.timeline.sample(self.stats.registry.snapshot()),
+                WriteReport(tx) => {
+                    if let Err(e) = self.stats.write_report() {
+                        warn!("failed to write stats report: {}", e);
+                    }
+                    if let Err(e) = self.stats.write_timeline() {
+                        warn!("failed to write timeline: {}", e);
+                    }
+                    let _ = tx.send(());
+                }
+                WriteMap(tx) => {
+                    if let Some(dir) = &self.stats.files.map_dir {
+                        if let Err(e) = self.stats.map.write_to(Path::new(dir)) {
+                            warn!("failed to write field map: {}", e);
+                        }
+                    }
+                    let _ = tx.send(());
+                }
             }
         }
     }
 }
 
+/// Requests are counted in the metrics registry, only snapshots of it are taken here.
+/// Shown stats and reports cover what happened since the last reset,
+/// the timeline and the field map always cover the whole game.
 pub struct Stats {
-    total: f64,
-    dig: EpMetric,
-    dig_found: f64,
-    dig_found_per_depth: BTreeMap<u8, (f64, f64)>,
-    cash: EpMetric,
-    cash_at_depth: EpMetric,
-    license: EpMetric,
-    licenses_per_coins: BTreeMap<u64, u64>,
-    digs_allowed_total: u64,
-    explore: EpMetric,
-    digs_with_found: HashMap<(u64, u64), u8>,
+    registry: Arc<Registry>,
+    baseline: Option<Snapshot>,
+    map: FieldMap,
+    // cells finished before the last reset
+    reset_cells: HashSet<(u64, u64)>,
+    timeline: Timeline,
+    files: StatsFiles,
 }
 
-pub struct EpMetric {
-    total: f64,
-    err: f64,
-    err_codes: HashSet<String>,
-    histograms: BTreeMap<u8, Histogram>,
-}
-
-impl EpMetric {
-    fn new() -> EpMetric {
-        EpMetric {
-            total: 0.,
-            err: 0.,
-            err_codes: HashSet::new(),
-            histograms: BTreeMap::new(),
-        }
-    }
-
-    fn inc(&mut self, map_key: u8, duration: u64, err: Option<StatusCode>) {
-        self.total += 1.;
-        self.histograms
-            .entry(map_key)
-            .or_insert_with(Histogram::new)
-            .increment(duration)
-            .expect("failed to update historgram stats");
-        // .map_err(|e| println!("hist err: {}", e));
-        if let Some(status) = err {
-            self.err += 1.;
-            self.err_codes.insert(status.to_string());
-        }
-    }
-}
-
-impl std::fmt::Display for EpMetric {
+impl std::fmt::Display for EndpointStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(
-            f,
-            "{} / {}, error rate {:.3}",
-            self.total,
-            self.err,
-            self.err / self.total
-        )?;
+        let (total, err) = (self.total() as f64, self.errors() as f64);
+        writeln!(f, "{} / {}, error rate {:.3}", total, err, err / total)?;
         // print percentiles from the histogram
         for (depth, histogram) in self.histograms.iter() {
             writeln!(
                 f,
-                "({}) - percentiles: p50: {} ns p90: {} ns p99: {} ns p999: {}",
+                "({}) - percentiles: p50: {} us p90: {} us p99: {} us p999: {} us",
                 depth,
-                histogram.percentile(50.0).unwrap(),
-                histogram.percentile(90.0).unwrap(),
-                histogram.percentile(99.0).unwrap(),
-                histogram.percentile(99.9).unwrap(),
+                histogram.percentile(50.0).unwrap_or(0),
+                histogram.percentile(90.0).unwrap_or(0),
+                histogram.percentile(99.0).unwrap_or(0),
+                histogram.percentile(99.9).unwrap_or(0),
             )?;
             writeln!(
                 f,
-                "({}) - latency (ns): Min: {} Avg: {} Max: {} StdDev: {}",
+                "({}) - latency (us): Min: {} Avg: {:.0} Max: {} StdDev: {:.0}",
                 depth,
-                histogram.minimum().unwrap(),
-                histogram.mean().unwrap(),
-                histogram.maximum().unwrap(),
-                histogram.stddev().unwrap(),
+                histogram.minimum().unwrap_or(0),
+                histogram.mean().unwrap_or(0.),
+                histogram.maximum().unwrap_or(0),
+                histogram.stddev().unwrap_or(0.),
             )?;
         }
-        if !self.err_codes.is_empty() {
+        if !self.statuses.is_empty() {
             writeln!(
                 f,
                 "codes {}",
-                self.err_codes
-                    .clone()
-                    .into_iter()
+                self.statuses
+                    .iter()
+                    .map(|(code, count)| match StatusCode::from_u16(*code) {
+                        Ok(status) => format!("{} x{}", status, count),
+                        Err(_) => format!("{} x{}", code, count),
+                    })
                     .collect::<Vec<String>>()
                     .join("|")
             )?;
         }
+        for (message, count) in self.samples.iter() {
+            writeln!(f, "error x{}: {}", count, message.trim_end())?;
+        }
         Ok(())
     }
 }
 
 impl std::fmt::Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // let mut contents = String::new();
-        // println!("size {}", self.digs_with_found.len());
-        // for (coord, count) in self.digs_with_found.iter() {
-        //     contents.push_str(format!("[{},{}:{}]\n", coord.0, coord.1, count).as_str());
-        // }
-        // let byt = deflate_bytes_conf(contents.as_bytes(), Compression::Best);
-        // let encoded = Base64Display::with_config(&byt, base64::STANDARD);
-        // write!(f, "{}", encoded)
-        writeln!(f, "total: {}", self.total)?;
-        write!(f, "explore: {}", self.explore)?;
+        let snapshot = self.snapshot();
+        writeln!(f, "total: {}", snapshot.total())?;
+        write!(f, "explore (log2 size buckets): {}", snapshot.explore)?;
+        if let Some(size) = ExploreCostModel::new(snapshot.explore.means()).knee_size() {
+            writeln!(f, "explore cost bends after size {}", size)?;
+        }
 
+        let dig_found = snapshot.dig_found();
         writeln!(
             f,
             "digs: {}found {}, found rate {}",
-            self.dig,
-            self.dig_found,
-            self.dig_found / self.dig.total
+            snapshot.dig,
+            dig_found,
+            dig_found / snapshot.dig.total() as f64
         )?;
-        let dig_stats: String = self
+        let dig_stats: String = snapshot
             .dig_found_per_depth
             .iter()
             .map(|(k, v)| format!("{}:{:.3}", k, v.1 / v.0))
@@ -172,80 +167,191 @@ impl std::fmt::Display for Stats {
             .join(", ");
         writeln!(f, "rate at depth {}", dig_stats)?;
 
-        write!(f, "cash: {}", self.cash)?;
-        writeln!(f, "cash at depth: {}", self.cash_at_depth)?;
+        let cells = self.cells();
+        writeln!(
+            f,
+            "cells dug: {}, digs {}, failed {}",
+            cells.dug, cells.digs, cells.failed
+        )?;
+
+        write!(f, "cash: {}", snapshot.cash)?;
+        writeln!(f, "cash at depth: {}", snapshot.cash_at_depth)?;
 
-        writeln!(f, "digs allowed total: {}", self.digs_allowed_total)?;
-        write!(f, "license: \n{}", self.license)?;
-        let lic_stats = self
+        writeln!(f, "digs allowed total: {}", snapshot.digs_allowed_total)?;
+        write!(f, "license: \n{}", snapshot.license)?;
+        let lic_stats = snapshot
             .licenses_per_coins
             .iter()
             .map(|(k, v)| format!("{} - {}", k, v))
             .collect::<Vec<String>>()
             .join("\n");
-        write!(f, "license per coins: {}", lic_stats)
+        writeln!(f, "license per coins: {}", lic_stats)?;
+
+        let endpoints = Endpoint::ALL
+            .iter()
+            .map(|e| format!("{} {}", e.index(), e.name()))
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "queue delay ({}): {}", endpoints, snapshot.queue)?;
+
+        let limits = snapshot
+            .limits
+            .iter()
+            .map(|(e, limit)| format!("{} {}", e.name(), limit))
+            .collect::<Vec<String>>()
+            .join(", ");
+        writeln!(f, "rate limits: {}", limits)?;
+
+        let timeouts = snapshot
+            .timeouts
+            .iter()
+            .map(|(e, count)| format!("{} {}", e.name(), count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        writeln!(f, "timeouts: {}", timeouts)?;
+        writeln!(f, "hedges won {} lost {}", snapshot.hedges.0, snapshot.hedges.1)?;
+
+        let breakers = Endpoint::ALL
+            .iter()
+            .map(|e| {
+                let opened = snapshot.breaker.get(&(*e, BreakerState::Open)).unwrap_or(&0);
+                format!("{} {}", e.name(), opened)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        writeln!(f, "circuit opened: {}", breakers)?;
+        let transitions = snapshot
+            .breaker
+            .iter()
+            .map(|((e, to), count)| format!("{} -> {} {}", e.name(), to.name(), count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        writeln!(f, "circuit transitions: {}", transitions)?;
+
+        write!(f, "per origin:")?;
+        for (origin, stats) in snapshot.origins.iter() {
+            write!(
+                f,
+                "\n{}: requests {}, error rate {:.3}, timeouts {}, digs {}, found rate {:.3}, cells {}",
+                origin,
+                stats.requests,
+                stats.error_rate(),
+                stats.timeouts,
+                stats.digs,
+                stats.found_rate(),
+                self.phase_cells().filter(|(o, _)| o == origin).count()
+            )?;
+        }
+
+        write!(f, "\nworker time:")?;
+        let workers = snapshot
+            .origins
+            .iter()
+            .filter(|(origin, _)| matches!(origin, Origin::Worker(_)));
+        for (worker, stats) in workers {
+            let times = &stats.times;
+            let shares = times
+                .spent_us
+                .iter()
+                .map(|(activity, us)| (activity.name(), *us))
+                .chain(std::iter::once(("other", times.other_us())))
+                .map(|(name, us)| format!("{} {:.1}%", name, 100. * times.share(us)))
+                .collect::<Vec<String>>()
+                .join(", ");
+            write!(
+                f,
+                "\n{} ({:.1} s): {}",
+                worker,
+                times.wall_us as f64 / 1e6,
+                shares
+            )?;
+        }
+        Ok(())
     }
 }
 
 impl Stats {
-    fn new() -> Stats {
+    fn new(registry: Arc<Registry>, files: StatsFiles, started: Instant) -> Stats {
         Stats {
-            total: 0.,
-            dig: EpMetric::new(),
-            dig_found: 0.,
-            dig_found_per_depth: BTreeMap::new(),
-            licenses_per_coins: BTreeMap::new(),
-            cash: EpMetric::new(),
-            cash_at_depth: EpMetric::new(),
-            license: EpMetric::new(),
-            explore: EpMetric::new(),
-            digs_with_found: HashMap::new(),
-            digs_allowed_total: 0,
+            registry,
+            baseline: None,
+            map: FieldMap::default(),
+            reset_cells: HashSet::new(),
+            timeline: Timeline::new(started),
+            files,
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let snapshot = self.registry.snapshot();
+        match &self.baseline {
+            Some(baseline) => snapshot.since(baseline),
+            None => snapshot,
         }
     }
 
-    fn record_dig(
-        &mut self,
-        duration: u64,
-        x: u64,
-        y: u64,
-        depth: u8,
-        found: bool,
-        err: Option<StatusCode>,
-    ) {
-        self.total += 1.;
-        self.dig.inc(depth, duration, err);
-
-        self.dig_found_per_depth.entry(depth).or_insert((0., 0.)).0 += 1.;
-        if found {
-            self.dig_found += 1.;
-            self.dig_found_per_depth.entry(depth).or_insert((0., 0.)).1 += 1.;
-            *self.digs_with_found.entry((x, y)).or_insert(0) += 1;
+    fn reset(&mut self) {
+        self.baseline = Some(self.registry.snapshot());
+        self.reset_cells = self.map.cells.keys().copied().collect();
+    }
+
+    fn phase_cells(&self) -> impl Iterator<Item = &(Origin, Vec<DigRecord>)> {
+        self.map
+            .cells
+            .iter()
+            .filter(move |(cell, _)| !self.reset_cells.contains(cell))
+            .map(|(_, history)| history)
+    }
+
+    fn cells(&self) -> CellsReport {
+        CellsReport {
+            dug: self.phase_cells().count() as u64,
+            digs: self.phase_cells().map(|(_, h)| h.len()).sum::<usize>() as u64,
+            failed: self
+                .phase_cells()
+                .flat_map(|(_, h)| h.iter())
+                .filter(|r| !matches!(r.outcome, DigOutcome::Found(_)))
+                .count() as u64,
         }
     }
 
-    fn record_cash(&mut self, duration: u64, depth: u8, amount: u64, err: Option<StatusCode>) {
-        self.total += 1.;
-        self.cash.inc(depth, duration, err);
-        self.cash_at_depth.inc(depth, amount, err);
+    fn report(&self) -> Report {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Report::new(&self.snapshot(), self.cells(), timestamp_ms)
+    }
+
+    fn write_report(&self) -> std::io::Result<()> {
+        match &self.files.report {
+            Some(path) => replace_file(path, &serde_json::to_vec_pretty(&self.report())?),
+            None => Ok(()),
+        }
     }
 
-    fn record_license(&mut self, duration: u64, coins: u64, allowed: u8, err: Option<StatusCode>) {
-        self.total += 1.;
-        self.license.inc(0, duration, err);
-        if err.is_none() {
-            *self.licenses_per_coins.entry(coins).or_insert(0) += 1;
-            self.digs_allowed_total += allowed as u64;
+    fn write_timeline(&self) -> std::io::Result<()> {
+        match &self.files.timeline {
+            Some(path) if path.ends_with(".csv") => {
+                replace_file(path, self.timeline.to_csv().as_bytes())
+            }
+            Some(path) => replace_file(path, &self.timeline.to_json()?),
+            None => Ok(()),
         }
     }
 
-    fn record_explore(&mut self, area_size: u64, duration: u64, err: Option<StatusCode>) {
-        self.total += 1.;
-        self.explore
-            .inc(if area_size == 1 { 1 } else { 2 }, duration, err);
+    fn record_cell(&mut self, origin: Origin, x: u64, y: u64, history: Vec<DigRecord>) {
+        self.map.cells.insert((x, y), (origin, history));
     }
 }
 
+/// Replaces the file at once so readers never see half of it.
+fn replace_file(path: &str, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(tmp, path)
+}
+
 impl Actor for StatsActor {
     fn start(mut self) {
         tokio::spawn(async move {
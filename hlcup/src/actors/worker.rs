@@ -1,23 +1,38 @@
 use crate::Rules;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::time::Instant;
 
+use reqwest::StatusCode;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
+use tracing::{debug, debug_span, warn, Instrument};
 
-use crate::constants::TIME_LIMIT_MS;
 use crate::http::client::{Client, ClientResponse};
 use crate::http::dto::{Area, Explore, License};
+use crate::http::error::ErrorKind;
 use crate::models::data::{PendingDig, Treasures};
-use crate::models::messages::MessageForAccounting;
+use crate::models::ledger::{DigLedger, DigOutcome, DigRecord};
+use crate::models::messages::{MessageForAccounting, StatsMessage};
+use crate::models::metrics::Activity;
+use crate::models::priority::{Prioritized, Score};
+use crate::models::task::{Job, Narrowing};
+use crate::models::trace::TraceId;
+use crate::strategy::{Context, Knowledge, SharedStrategy};
 
 pub struct Worker {
     client: Client,
     rules: Rules,
+    strategy: SharedStrategy,
+    knowledge: Knowledge,
     license: Option<License>,
-    explore_heap: BinaryHeap<Explore>,
-    dig_heap: BinaryHeap<PendingDig>,
+    // one heap per region of the bandit
+    explore_heaps: Vec<BinaryHeap<Prioritized<Job<Narrowing>>>>,
+    dig_heap: BinaryHeap<Prioritized<Job<PendingDig>>>,
+    ledger: DigLedger,
+    // treasures not found yet in blind-dug areas of known amount, by the trace of the area
+    unfound: HashMap<TraceId, u64>,
     accounting_handle: mpsc::Sender<MessageForAccounting>,
+    stats_handler: mpsc::Sender<StatsMessage>,
 }
 
 impl Worker {
@@ -26,7 +41,11 @@ impl Worker {
             match self.logic().await {
                 Ok(_) => (),
                 Err(e) => {
-                    println!("error {}", e)
+                    warn!(error = %e.message, "worker step failed");
+                    if let ErrorKind::CircuitOpen { retry_in } = e.kind {
+                        tokio::time::sleep(retry_in).await;
+                        self.client.metrics().record_time(Activity::Idle, retry_in);
+                    }
                 }
             }
         }
@@ -35,66 +54,119 @@ impl Worker {
     pub async fn new(
         client: Client,
         rules: Rules,
+        strategy: SharedStrategy,
         started: Instant,
         areas: Vec<Area>,
         accounting_handle: mpsc::Sender<MessageForAccounting>,
+        stats_handler: mpsc::Sender<StatsMessage>,
     ) -> Self {
-        let explore_heap = Worker::init_state(&client, &rules, started, areas)
-            .await
+        let mut knowledge = Knowledge::new(started, client.registry(), &rules, areas.clone());
+        let explore_heap =
+            Worker::init_state(&client, &rules, &strategy, &mut knowledge, &stats_handler, areas)
+                .await
             .expect("failed to initialize worker state");
 
-        Self {
+        let explore_heaps = (0..knowledge.bandit.regions())
+            .map(|_| BinaryHeap::new())
+            .collect::<Vec<_>>();
+
+        let mut worker = Self {
             client,
             rules,
+            strategy,
+            knowledge,
             license: None,
-            explore_heap,
-            dig_heap: BinaryHeap::<PendingDig>::new(),
+            explore_heaps,
+            dig_heap: BinaryHeap::new(),
+            ledger: DigLedger::new(),
+            unfound: HashMap::new(),
             accounting_handle,
+            stats_handler,
+        };
+        for explore in explore_heap {
+            worker.push_explore(explore.into_inner(), None);
         }
+        worker
     }
 
     // todo: get rid of it
     async fn init_state(
         client: &Client,
         rules: &Rules,
-        started: Instant,
+        strategy: &SharedStrategy,
+        knowledge: &mut Knowledge,
+        stats_handler: &mpsc::Sender<StatsMessage>,
         areas: Vec<Area>,
-    ) -> ClientResponse<BinaryHeap<Explore>> {
+    ) -> ClientResponse<BinaryHeap<Prioritized<Explore>>> {
+        let prioritize =
+            |e: Explore, ctx: &Context| Prioritized::new(e, |e| strategy.explore_score(e, ctx));
         let mut errors = BinaryHeap::new();
-        areas.into_iter().for_each(|area| {
-            errors.push(Explore {
-                area,
-                amount: u64::max_value(),
-            })
-        });
+        {
+            let ctx = knowledge.context(rules.max_depth);
+            areas.into_iter().for_each(|area| {
+                errors.push(prioritize(
+                    Explore {
+                        area,
+                        amount: u64::MAX,
+                    },
+                    &ctx,
+                ))
+            });
+        }
         let mut explore_heap = BinaryHeap::new();
-        while let Some(a) = errors.pop() {
-            match client.explore(&a.area).await {
-                Ok(result) if result.is_managable(started, rules.max_depth) => {
-                    explore_heap.push(result);
+        while let Some(a) = errors.pop().map(Prioritized::into_inner) {
+            // splits below get to use latencies measured by the explores so far
+            let now = Instant::now();
+            knowledge.planner.refresh();
+            client.metrics().record_time(Activity::Stats, now.elapsed());
+            let now = Instant::now();
+            let result = client.explore(&a.area).await;
+            client.metrics().record_time(Activity::Explore, now.elapsed());
+            if let Ok(result) = &result {
+                knowledge.density.observe(result, a.amount);
+                record_area(stats_handler, result);
+            }
+            let ctx = knowledge.context(rules.max_depth);
+            match result {
+                Ok(result) if strategy.is_managable(&result, &ctx) => {
+                    explore_heap.push(prioritize(result, &ctx));
                 }
                 Ok(result) => {
                     let amount = result.amount;
-                    errors.extend(result.area.divide().into_iter().map(|area| Explore { area, amount }))
+                    errors.extend(
+                        strategy
+                            .split(&result, &ctx)
+                            .into_iter()
+                            .map(|area| prioritize(Explore { area, amount }, &ctx)),
+                    )
                 },
-                Err(_) => errors.extend(a.area.divide().into_iter().map(|a| Explore {
-                    area: a,
-                    amount: u64::max_value(),
+                // digging it right away may beat exploring it again when the field is dense
+                Err(_) if strategy.blind_dig(&a, &ctx) => explore_heap.push(prioritize(a, &ctx)),
+                Err(_) => errors.extend(strategy.split(&a, &ctx).into_iter().map(|a| {
+                    prioritize(
+                        Explore {
+                            area: a,
+                            amount: u64::MAX,
+                        },
+                        &ctx,
+                    )
                 })),
             }
         }
 
+        let now = Instant::now();
+        knowledge.planner.refresh();
+        client.metrics().record_time(Activity::Stats, now.elapsed());
+        let ctx = knowledge.context(rules.max_depth);
         let mut ff = BinaryHeap::new();
-        let mut cum_cost = 0;
+        let mut cum_cost = 0.;
         while let Some(e) = explore_heap.pop() {
             // todo: skip this if
-            if e.is_managable(started, rules.max_depth) {
-                cum_cost += e.cost(rules.max_depth);
+            if strategy.is_managable(e.item(), &ctx) {
+                cum_cost += ctx.planner.cost_us(e.item(), rules.max_depth);
                 ff.push(e);
 
-                let time_since_started_ms = started.elapsed().as_millis();
-                let remaining_time_ms = TIME_LIMIT_MS - time_since_started_ms;
-                if cum_cost > remaining_time_ms {
+                if cum_cost > ctx.planner.remaining_us() {
                     break;
                 }
             }
@@ -111,91 +183,371 @@ impl Worker {
     }
 
     async fn logic(&mut self) -> ClientResponse<()> {
-        if let Some(ar) = self.explore_heap.pop() {
-            // todo: if we have total we do not need to get latest from here
-            // since it can be computed given previous results
-            match ar.area.size() {
-                1 => {
-                    self.dig_heap
-                        .push(PendingDig::new(ar.area.pos_x, ar.area.pos_y, ar.amount));
+        let now = Instant::now();
+        self.knowledge.planner.refresh();
+        self.client.metrics().record_time(Activity::Stats, now.elapsed());
+
+        if let Some(job) = self.pop_explore() {
+            let span = debug_span!("explore", trace = job.task.trace, attempt = job.attempts);
+            self.explore_step(job).instrument(span).await?;
+        }
+
+        // todo: ordering
+        if let Some(job) = self.pop_dig() {
+            let dig = &job.task;
+            let span = debug_span!(
+                "dig",
+                trace = dig.trace,
+                x = dig.x,
+                y = dig.y,
+                depth = dig.depth,
+                attempt = job.attempts
+            );
+            self.dig_step(job).instrument(span).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn explore_step(&mut self, mut job: Job<Narrowing>) -> ClientResponse<()> {
+        let ar = &job.task.explore;
+        let (area, amount) = (ar.area.clone(), ar.amount);
+        let region = self.knowledge.bandit.region_of(area.pos_x, area.pos_y);
+        // todo: if we have total we do not need to get latest from here
+        // since it can be computed given previous results
+        match area.size() {
+            1 => {
+                self.push_dig(PendingDig::new(area.pos_x, area.pos_y, amount, job.task.trace));
+            }
+            _ if job.task.remaining.is_none()
+                && self.strategy.blind_dig(ar, &self.context()) =>
+            {
+                let per_cell = if amount == u64::MAX {
+                    // treasures are spread over the cells, none is expected to hold them all
+                    self.knowledge.density.cell_treasures(ar)
+                } else {
+                    // any cell may hold them all, digging stops once they are all found
+                    self.unfound.insert(job.task.trace, amount);
+                    amount
+                };
+                for (x, y) in area.cells() {
+                    self.push_dig(PendingDig::new(x, y, per_cell, job.task.trace));
                 }
-                _ => {
-                    let mut divided = ar.area.divide();
-                    let last = divided.pop();
-                    let mut cum = 0;
-                    for a in divided.iter() {
-                        let res = self.client.explore(a).await?;
-                        if res.amount > 0 {
-                            cum += res.amount;
-                            self.explore_heap.push(res);
-                            if cum == ar.amount {
-                                break;
-                            }
-                        };
+            }
+            _ => {
+                let mut divided = match job.task.remaining.take() {
+                    Some(remaining) => remaining,
+                    None => self.strategy.split(ar, &self.context()),
+                };
+                // the last part is inferred only when the area's amount is known
+                let last = if amount == u64::MAX { None } else { divided.pop() };
+                let mut cum = job.task.found;
+                for (i, a) in divided.iter().enumerate() {
+                    if cum == amount {
+                        break;
                     }
-                    if ar.amount > cum {
-                        if let Some(area) = last {
-                            self.explore_heap.push(Explore {
-                                area,
-                                amount: ar.amount - cum,
-                            });
+                    self.knowledge.bandit.record_request(region);
+                    let now = Instant::now();
+                    let res = self.client.explore(a).await;
+                    self.client.metrics().record_time(Activity::Explore, now.elapsed());
+                    let res = match res {
+                        Ok(res) => res,
+                        Err(e) => {
+                            // children found so far are already on the heaps
+                            job.task.suspend(divided, i, last, cum);
+                            match e.kind {
+                                // nothing was sent, it is not an attempt
+                                ErrorKind::CircuitOpen { .. } => self.requeue_explore(job),
+                                _ => self.retry_explore(job),
+                            }
+                            return Err(e);
                         }
+                    };
+                    self.knowledge.density.observe(&res, amount);
+                    record_area(&self.stats_handler, &res);
+                    if res.amount > 0 {
+                        cum += res.amount;
+                        self.push_explore(res, Some(job.task.trace));
+                    };
+                }
+                if amount > cum {
+                    if let Some(area) = last {
+                        let explore = Explore {
+                            area,
+                            amount: amount - cum,
+                        };
+                        self.push_explore(explore, Some(job.task.trace));
                     }
-                    // todo: checks
-                    // assert_eq!(ar.amount, cum);
                 }
+                // todo: checks
+                // assert_eq!(ar.amount, cum);
             }
         }
+        Ok(())
+    }
 
-        // todo: ordering
-        if let Some(pending_dig) = self.dig_heap.pop() {
-            match &mut self.license {
-                Some(lic) => {
-                    let treasure = self.client.dig(&pending_dig.to_dig(lic.id)).await?;
-
-                    let treasures_count = treasure.len() as u64;
-                    if let Some(next_level) =
-                        pending_dig.next_level(self.rules.max_depth, treasures_count)
-                    {
-                        self.dig_heap.push(next_level);
+    async fn dig_step(&mut self, job: Job<PendingDig>) -> ClientResponse<()> {
+        let pending_dig = &job.task;
+        let (x, y, depth) = (pending_dig.x, pending_dig.y, pending_dig.depth);
+        match &mut self.license {
+            Some(lic) => {
+                let license = lic.id;
+                let region = self.knowledge.bandit.region_of(x, y);
+                self.knowledge.bandit.record_request(region);
+                let dig = pending_dig.to_dig(license);
+                let now = Instant::now();
+                let res = self.client.dig(&dig).await;
+                self.client.metrics().record_time(Activity::Dig, now.elapsed());
+                let (treasure, expired) = match res {
+                    Ok(treasure) => (treasure, !lic.increment()),
+                    Err(e) => {
+                        let outcome = match e.kind {
+                            ErrorKind::Timeout | ErrorKind::Transport => Some(DigOutcome::Unknown),
+                            ErrorKind::Status(status) if status.is_server_error() => {
+                                Some(DigOutcome::Unknown)
+                            }
+                            ErrorKind::Status(StatusCode::UNPROCESSABLE_ENTITY) => {
+                                Some(DigOutcome::WrongDepth)
+                            }
+                            ErrorKind::Status(_) => Some(DigOutcome::Failed),
+                            // nothing was sent
+                            ErrorKind::CircuitOpen { .. } => None,
+                        };
+                        let rejected_license = e.kind == ErrorKind::Status(StatusCode::FORBIDDEN);
+                        // a dig that may have gone through may have used up the license as well
+                        let expired = rejected_license
+                            || (outcome == Some(DigOutcome::Unknown) && !lic.increment());
+                        match outcome {
+                            // the license is to blame, not the dig
+                            Some(outcome) if rejected_license => {
+                                self.ledger.record(x, y, DigRecord { depth, license, outcome });
+                                self.requeue_dig(job);
+                            }
+                            Some(outcome) => {
+                                self.ledger.record(x, y, DigRecord { depth, license, outcome });
+                                self.retry_dig(job);
+                            }
+                            None => self.requeue_dig(job),
+                        }
+                        if expired {
+                            self.expire_license().await;
+                        }
+                        return Err(e);
                     }
+                };
 
-                    if treasures_count > 0 {
-                        self.accounting_handle
-                            .send(MessageForAccounting::TreasureToClaim(Treasures {
-                                depth: pending_dig.depth,
-                                treasures: treasure,
-                            }))
-                            .await
-                            .expect("failed to send treasure");
-                    }
-                    if !lic.increment() {
-                        self.license = None;
-                        self.accounting_handle
-                            .send(MessageForAccounting::LicenseExpired(self.pending_digs()))
-                            .await
-                            .expect("failed to notify for license expiration");
-                    }
+                let treasures_count = treasure.len() as u64;
+                if treasures_count > 0 {
+                    debug!(count = treasures_count, "treasure found");
                 }
-                None => {
-                    self.dig_heap.push(pending_dig);
-                    let (tx, rx) = oneshot::channel();
+                let outcome = DigOutcome::Found(treasures_count);
+                self.ledger.record(x, y, DigRecord { depth, license, outcome });
+                match self
+                    .strategy
+                    .next_level(pending_dig, &self.context(), treasures_count)
+                {
+                    Some(next_level) => self.push_dig(next_level),
+                    None => self.finish_cell(x, y),
+                }
+
+                if treasures_count > 0 {
+                    self.found_in_area(pending_dig.trace, treasures_count);
+                    let coins = self.knowledge.planner.depth_model().coins(pending_dig.depth);
+                    self.knowledge.bandit.record_coins(region, treasures_count as f64 * coins);
+                    self.knowledge.hits.record(pending_dig.x, pending_dig.y, treasures_count);
+                    self.rescore_explores(pending_dig.x, pending_dig.y);
+                    let now = Instant::now();
                     self.accounting_handle
-                        .send(MessageForAccounting::GetLicense(tx))
+                        .send(MessageForAccounting::TreasureToClaim(Treasures {
+                            depth: pending_dig.depth,
+                            treasures: treasure,
+                            trace: pending_dig.trace,
+                        }))
                         .await
-                        .expect("failed to request license");
-                    self.license = rx.await.expect("failed to receive license")
+                        .expect("failed to send treasure");
+                    self.client.metrics().record_time(Activity::Mailbox, now.elapsed());
+                }
+                if expired {
+                    self.expire_license().await;
                 }
             }
-        };
-
+            None => {
+                self.requeue_dig(job);
+                let now = Instant::now();
+                let (tx, rx) = oneshot::channel();
+                self.accounting_handle
+                    .send(MessageForAccounting::GetLicense(tx))
+                    .await
+                    .expect("failed to request license");
+                self.license = rx.await.expect("failed to receive license");
+                self.client.metrics().record_time(Activity::LicenseWait, now.elapsed());
+            }
+        }
         Ok(())
     }
 
+    async fn expire_license(&mut self) {
+        self.license = None;
+        let now = Instant::now();
+        self.accounting_handle
+            .send(MessageForAccounting::LicenseExpired(self.pending_digs()))
+            .await
+            .expect("failed to notify for license expiration");
+        self.client.metrics().record_time(Activity::Mailbox, now.elapsed());
+    }
+
+    fn context(&self) -> Context<'_> {
+        self.knowledge.context(self.rules.max_depth)
+    }
+
+    fn pop_explore(&mut self) -> Option<Job<Narrowing>> {
+        let tops = self
+            .explore_heaps
+            .iter()
+            .enumerate()
+            .filter_map(|(region, heap)| heap.peek().map(|top| (region, top.score())))
+            .collect::<Vec<(usize, Score)>>();
+        let region = self.strategy.pick_region(&tops, &self.context())?;
+        self.explore_heaps[region].pop().map(Prioritized::into_inner)
+    }
+
+    fn push_explore(&mut self, explore: Explore, parent: Option<TraceId>) {
+        let narrowing = Narrowing::new(explore);
+        debug!(
+            trace = narrowing.trace,
+            parent = ?parent,
+            area = ?narrowing.explore.area,
+            amount = narrowing.explore.amount,
+            "area with treasure"
+        );
+        self.requeue_explore(Job::new(narrowing));
+    }
+
+    fn requeue_explore(&mut self, job: Job<Narrowing>) {
+        if job.attempts > 0 {
+            debug!(trace = job.task.trace, area = ?job.task.explore.area, attempt = job.attempts, "requeue explore");
+        }
+        let area = &job.task.explore.area;
+        let region = self.knowledge.bandit.region_of(area.pos_x, area.pos_y);
+        let job = Prioritized::new(job, |j| {
+            j.priority(self.strategy.explore_score(&j.task.explore, &self.context()))
+        });
+        self.explore_heaps[region].push(job);
+    }
+
+    fn retry_explore(&mut self, job: Job<Narrowing>) {
+        let (trace, area) = (job.task.trace, job.task.explore.area.clone());
+        match job.retry() {
+            Some(job) => self.requeue_explore(job),
+            None => warn!(trace, area = ?area, "explore given up after too many attempts"),
+        }
+    }
+
+    /// Scores depend on hits around the areas, only the ones next to a new hit are rescored.
+    fn rescore_explores(&mut self, x: u64, y: u64) {
+        let hits = &self.knowledge.hits;
+        let near = |job: &Prioritized<Job<Narrowing>>| hits.touches(&job.item().task.explore.area, x, y);
+        let mut rescored = vec![];
+        for heap in self.explore_heaps.iter_mut() {
+            if heap.iter().any(near) {
+                let (touched, kept): (Vec<_>, Vec<_>) =
+                    std::mem::take(heap).into_vec().into_iter().partition(near);
+                *heap = BinaryHeap::from(kept);
+                rescored.extend(touched);
+            }
+        }
+        for job in rescored {
+            self.requeue_explore(job.into_inner());
+        }
+    }
+
+    fn push_dig(&mut self, dig: PendingDig) {
+        self.requeue_dig(Job::new(dig));
+    }
+
+    fn requeue_dig(&mut self, job: Job<PendingDig>) {
+        if job.attempts > 0 {
+            debug!(trace = job.task.trace, dig = ?job.task, attempt = job.attempts, "requeue dig");
+        }
+        let job = Prioritized::new(job, |j| {
+            j.priority(self.strategy.dig_score(&j.task, &self.context()))
+        });
+        self.dig_heap.push(job);
+    }
+
+    fn retry_dig(&mut self, job: Job<PendingDig>) {
+        let (x, y, trace) = (job.task.x, job.task.y, job.task.trace);
+        match job.retry() {
+            Some(job) => self.requeue_dig(job),
+            None => {
+                warn!(trace, x, y, "dig given up after too many attempts");
+                self.finish_cell(x, y);
+            }
+        }
+    }
+
+    fn finish_cell(&mut self, x: u64, y: u64) {
+        let history = self.ledger.finish(x, y);
+        let origin = self.client.metrics().origin();
+        record(&self.stats_handler, StatsMessage::RecordCell { origin, x, y, history });
+    }
+
+    /// Next dig, moved to the level its cell is ready for.
+    /// Cells with every level dug are finished instead.
+    fn pop_dig(&mut self) -> Option<Job<PendingDig>> {
+        loop {
+            let mut job = self.dig_heap.pop().map(Prioritized::into_inner)?;
+            if let Some(unfound) = self.unfound.get(&job.task.trace) {
+                job.task.remaining = job.task.remaining.min(*unfound);
+            }
+            if self.ledger.align(&mut job.task, self.rules.max_depth) {
+                return Some(job);
+            }
+            self.finish_cell(job.task.x, job.task.y);
+        }
+    }
+
+    /// Once every treasure of a blind-dug area is found the rest of its cells are finished.
+    fn found_in_area(&mut self, trace: TraceId, found: u64) {
+        let unfound = match self.unfound.get_mut(&trace) {
+            Some(unfound) => unfound,
+            None => return,
+        };
+        *unfound = unfound.saturating_sub(found);
+        if *unfound > 0 {
+            return;
+        }
+        self.unfound.remove(&trace);
+        let (done, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.dig_heap)
+            .into_vec()
+            .into_iter()
+            .partition(|job| job.item().task.trace == trace);
+        self.dig_heap = BinaryHeap::from(kept);
+        for job in done {
+            let dig = &job.item().task;
+            self.finish_cell(dig.x, dig.y);
+        }
+    }
+
     fn pending_digs(&self) -> u64 {
         self.dig_heap
             .iter()
-            .map(|pd| (self.rules.max_depth + 1 - pd.depth) as u64)
+            .map(|pd| (self.rules.max_depth + 1 - pd.item().task.depth) as u64)
             .sum()
     }
 }
+
+/// Map records are dropped rather than waited for when the stats actor falls behind.
+fn record(stats_handler: &mpsc::Sender<StatsMessage>, msg: StatsMessage) {
+    if let Err(e) = stats_handler.try_send(msg) {
+        debug!(error = %e, "stats record dropped");
+    }
+}
+
+fn record_area(stats_handler: &mpsc::Sender<StatsMessage>, explore: &Explore) {
+    // single cells show up on the dug and found maps
+    if explore.area.size() > 1 {
+        let (area, amount) = (explore.area.clone(), explore.amount);
+        record(stats_handler, StatsMessage::RecordArea { area, amount });
+    }
+}
@@ -1,2 +1,22 @@
-pub const TIME_LIMIT_MS: u128 = 600 * 1000; // 1 minute
-pub const AVG_DIG_MS: u128 = 2;
+pub const TIME_LIMIT_MS: u128 = 600 * 1000; // 10 minutes
+
+// priors used by the planner until the first latencies are measured
+pub const DEFAULT_EXPLORE_US: f64 = 2000.;
+pub const DEFAULT_DIG_US: f64 = 2000.;
+pub const DEFAULT_CASH_US: f64 = 2000.;
+pub const PLANNER_REFRESH_MS: u128 = 1000;
+// quadtree levels explored at once when an area is split
+pub const MAX_SPLIT_LEVELS: u32 = 4;
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+// failed explores and digs are given up on after this many attempts
+pub const MAX_ATTEMPTS: u32 = 5;
+// explores still running past this latency percentile get a duplicate
+pub const HEDGE_PERCENTILE: f64 = 95.;
+
+// length of a time series window
+pub const TIMELINE_WINDOW_MS: u64 = 1000;
+// seconds between reports when STATS_FILE or TIMELINE_FILE is set
+pub const REPORT_INTERVAL_S: u64 = 10;
+// explored areas kept for the field map, later ones are left off the map
+pub const MAX_MAP_AREAS: usize = 200_000;
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::http::scheduler::Endpoint;
+
+// consecutive failures that open the circuit
+const FAILURE_THRESHOLD: u32 = 5;
+// how long an open circuit fails fast before letting a probe through
+const OPEN_MS: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub const ALL: [BreakerState; 3] = [
+        BreakerState::Closed,
+        BreakerState::Open,
+        BreakerState::HalfOpen,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half-open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: BreakerState,
+    pub to: BreakerState,
+}
+
+struct Circuit {
+    state: BreakerState,
+    failures: u32,
+    opened: Instant,
+    // the single request let through while half-open, a lost one is replaced after `OPEN_MS`
+    probe: Option<Instant>,
+}
+
+impl Circuit {
+    fn move_to(&mut self, to: BreakerState) -> Option<Transition> {
+        let from = self.state;
+        self.state = to;
+        match to {
+            BreakerState::Closed => self.failures = 0,
+            BreakerState::Open => self.opened = Instant::now(),
+            BreakerState::HalfOpen => self.probe = None,
+        }
+        if from != to {
+            Some(Transition { from, to })
+        } else {
+            None
+        }
+    }
+}
+
+/// Circuit breaker for one endpoint. After `FAILURE_THRESHOLD` failures in a row
+/// requests fail fast for `OPEN_MS`, then a single probe decides whether it closes again.
+pub struct Breaker {
+    circuit: Mutex<Circuit>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Breaker {
+    pub fn new() -> Self {
+        Self {
+            circuit: Mutex::new(Circuit {
+                state: BreakerState::Closed,
+                failures: 0,
+                opened: Instant::now(),
+                probe: None,
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit.lock().expect("breaker lock poisoned")
+    }
+
+    /// Lets a request through or tells how long until the circuit may let one through.
+    pub fn enter(&self) -> (Result<(), Duration>, Option<Transition>) {
+        let open_for = Duration::from_millis(OPEN_MS);
+        let mut circuit = self.lock();
+        let mut transition = None;
+        if circuit.state == BreakerState::Open {
+            let elapsed = circuit.opened.elapsed();
+            if elapsed < open_for {
+                return (Err(open_for - elapsed), None);
+            }
+            transition = circuit.move_to(BreakerState::HalfOpen);
+        }
+        if circuit.state == BreakerState::HalfOpen {
+            if let Some(probe) = circuit.probe {
+                if probe.elapsed() < open_for {
+                    return (Err(open_for - probe.elapsed()), transition);
+                }
+            }
+            circuit.probe = Some(Instant::now());
+        }
+        (Ok(()), transition)
+    }
+
+    pub fn record(&self, success: bool) -> Option<Transition> {
+        let mut circuit = self.lock();
+        match (circuit.state, success) {
+            (BreakerState::Closed, true) => {
+                circuit.failures = 0;
+                None
+            }
+            (BreakerState::Closed, false) => {
+                circuit.failures += 1;
+                if circuit.failures >= FAILURE_THRESHOLD {
+                    circuit.move_to(BreakerState::Open)
+                } else {
+                    None
+                }
+            }
+            (BreakerState::HalfOpen, true) => circuit.move_to(BreakerState::Closed),
+            (BreakerState::HalfOpen, false) => circuit.move_to(BreakerState::Open),
+            // answers to requests sent before the circuit opened
+            (BreakerState::Open, _) => None,
+        }
+    }
+}
+
+pub struct Breakers {
+    breakers: HashMap<Endpoint, Breaker>,
+}
+
+impl Breakers {
+    pub fn new() -> Self {
+        Self {
+            breakers: Endpoint::ALL.iter().map(|e| (*e, Breaker::new())).collect(),
+        }
+    }
+
+    pub fn get(&self, endpoint: Endpoint) -> &Breaker {
+        &self.breakers[&endpoint]
+    }
+}
+
+impl Default for Breakers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,79 +2,190 @@ use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use std::sync::Arc;
 use std::time::Instant;
 
-use tokio::sync::mpsc;
+use tracing::{debug, debug_span, info, Instrument};
 
+use crate::http::breaker::{Breakers, Transition};
+use crate::http::deadlines::Deadlines;
 use crate::http::dto::*;
-use crate::http::error::DescriptiveError;
+use crate::http::error::{DescriptiveError, ErrorKind};
+use crate::http::hedge::Hedging;
+use crate::http::limiter::RateLimits;
+use crate::http::scheduler::{Endpoint, Scheduler};
+use crate::http::transport::Transport;
+use crate::models::cost::size_bucket;
 use crate::models::data::Treasure;
-use crate::models::messages::StatsMessage;
-use crate::models::messages::StatsMessage::*;
+use crate::models::metrics::{Metrics, Origin, Registry};
 
 pub type ClientResponse<T> = Result<T, DescriptiveError>;
 
+// response body read while the transport still holds it
+enum Body<R> {
+    Parsed(R),
+    Malformed(serde_json::Error),
+    Text(String),
+}
+
 #[derive(Clone)]
 pub struct Client {
-    client: reqwest::Client,
-    explore_url: String,
-    licenses_url: String,
-    dig_url: String,
-    cash_url: String,
-    stats_handler: mpsc::Sender<StatsMessage>,
+    transport: Transport,
+    scheduler: Arc<Scheduler>,
+    limits: Arc<RateLimits>,
+    deadlines: Arc<Deadlines>,
+    breakers: Arc<Breakers>,
+    // only explores are hedged, sending one twice is harmless
+    hedging: Option<Arc<Hedging>>,
+    registry: Arc<Registry>,
+    metrics: Arc<Metrics>,
 }
 
 impl Client {
-    pub fn new(address: &str, stats_handler: mpsc::Sender<StatsMessage>) -> Client {
-        let client = reqwest::Client::new();
-        let base_url = format!("http://{}:8000", address);
-        println!("Base url {}", base_url);
+    pub fn new(
+        address: &str,
+        scheduler: Arc<Scheduler>,
+        deadlines: Deadlines,
+        hedging: bool,
+        registry: Arc<Registry>,
+    ) -> Client {
+        let authority = format!("{}:8000", address);
+        info!("Base url http://{}", authority);
+        Client {
+            transport: Transport::new(&authority),
+            limits: Arc::new(RateLimits::new(scheduler.max_in_flight())),
+            scheduler,
+            deadlines: Arc::new(deadlines),
+            breakers: Arc::new(Breakers::new()),
+            hedging: if hedging {
+                Some(Arc::new(Hedging::new(Endpoint::Explore, registry.clone())))
+            } else {
+                None
+            },
+            metrics: registry.shard(Origin::Accounting),
+            registry,
+        }
+    }
+
+    /// Copy for a single worker, with its own metrics shard
+    /// and its own connections when the transport keeps them.
+    pub fn for_worker(&self, worker: u64) -> Client {
         Client {
-            client,
-            explore_url: base_url.clone() + "/explore",
-            licenses_url: base_url.clone() + "/licenses",
-            dig_url: base_url.clone() + "/dig",
-            cash_url: base_url + "/cash",
-            stats_handler,
+            transport: self.transport.detached(),
+            metrics: self.registry.shard(Origin::Worker(worker)),
+            ..self.clone()
         }
     }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn registry(&self) -> Arc<Registry> {
+        self.registry.clone()
+    }
 }
 
 impl Client {
-    async fn send_stats(&self, message: StatsMessage) {
-        self.stats_handler
-            .send(message)
-            .await
-            .expect("failed to send stats");
+    fn failed(&self, endpoint: Endpoint, e: DescriptiveError) -> DescriptiveError {
+        if e.kind == ErrorKind::Timeout {
+            self.metrics.record_timeout(endpoint);
+        }
+        self.metrics.record_error(endpoint, &e.message);
+        e
+    }
+
+    fn record_transition(&self, endpoint: Endpoint, transition: Option<Transition>) {
+        if let Some(Transition { from, to }) = transition {
+            info!(endpoint = endpoint.name(), from = from.name(), to = to.name(), "circuit");
+            self.metrics.record_breaker(endpoint, to);
+        }
     }
 
+    /// Fails fast with `ErrorKind::CircuitOpen` while the endpoint keeps failing.
     async fn call<Payload: Serialize, Response: DeserializeOwned + Default>(
         &self,
-        endpoint: &str,
+        endpoint: Endpoint,
         payload: &Payload,
-        stats_success: impl Fn(&Response, u64) -> StatsMessage,
-        stats_failure: impl Fn(Option<StatusCode>, u64) -> StatsMessage,
+        stats_success: impl Fn(&Metrics, &Response, u64),
+        stats_failure: impl Fn(&Metrics, Option<StatusCode>, u64),
         error_info: Option<String>,
     ) -> ClientResponse<Response> {
+        let breaker = self.breakers.get(endpoint);
+        let (entered, transition) = breaker.enter();
+        self.record_transition(endpoint, transition);
+        if let Err(retry_in) = entered {
+            return Err(DescriptiveError::circuit_open(self.transport.url(endpoint), retry_in));
+        }
+
+        let span = debug_span!("http", endpoint = endpoint.name());
+        let res = self
+            .send(endpoint, payload, stats_success, stats_failure, error_info)
+            .instrument(span)
+            .await;
+        let transition = breaker.record(res.is_ok());
+        self.record_transition(endpoint, transition);
+        res
+    }
+
+    async fn send<Payload: Serialize, Response: DeserializeOwned + Default>(
+        &self,
+        endpoint: Endpoint,
+        payload: &Payload,
+        stats_success: impl Fn(&Metrics, &Response, u64),
+        stats_failure: impl Fn(&Metrics, Option<StatusCode>, u64),
+        error_info: Option<String>,
+    ) -> ClientResponse<Response> {
+        let queued = Instant::now();
+        let limiter = self.limits.get(endpoint);
+        // both held until the response body is read
+        let _limited = limiter.acquire().await;
+        let _permit = self.scheduler.acquire(endpoint).await;
+        self.metrics
+            .record_queue(endpoint, queued.elapsed().as_micros() as u64);
+
         let now = Instant::now();
-        let response = self.client.post(endpoint).json(payload).send().await?;
+        let response = self
+            .transport
+            .post(endpoint, payload, self.deadlines.get(endpoint), |raw| {
+                let body = match raw.status {
+                    reqwest::StatusCode::OK => match serde_json::from_slice::<Response>(raw.body) {
+                        Ok(res) => Body::Parsed(res),
+                        Err(e) => Body::Malformed(e),
+                    },
+                    _ => Body::Text(String::from_utf8_lossy(raw.body).into_owned()),
+                };
+                (raw.status, body)
+            })
+            .await;
         let elapsed = now.elapsed().as_micros() as u64;
+        let status = response.as_ref().ok().map(|(status, _)| *status);
+        debug!(elapsed_us = elapsed, status = ?status, "response");
+        if let Some(limit) = limiter.record(elapsed, status) {
+            self.metrics.record_limit(endpoint, limit);
+        }
+        let (status, body) = match response {
+            Ok(response) => response,
+            Err(e) => return Err(self.failed(endpoint, e)),
+        };
 
-        match response.status() {
-            reqwest::StatusCode::OK => {
-                let res = response.json::<Response>().await?;
-                self.send_stats(stats_success(&res, elapsed)).await;
+        match body {
+            Body::Parsed(res) => {
+                stats_success(&self.metrics, &res, elapsed);
                 Ok(res)
             }
-            reqwest::StatusCode::NOT_FOUND if endpoint == self.dig_url => {
-                self.send_stats(stats_failure(None, elapsed)).await;
+            Body::Malformed(e) => Err(self.failed(endpoint, e.into())),
+            Body::Text(_) if status == reqwest::StatusCode::NOT_FOUND && endpoint == Endpoint::Dig => {
+                stats_failure(&self.metrics, None, elapsed);
                 Ok(Response::default())
             }
-            status => {
-                self.send_stats(stats_failure(Some(status), elapsed)).await;
-                let error_text = response.text().await?;
+            Body::Text(error_text) => {
+                stats_failure(&self.metrics, Some(status), elapsed);
+                // without the request details so that the same rejection is counted once
+                self.metrics
+                    .record_error(endpoint, &format!("{} {}", status.as_u16(), error_text));
                 Err(DescriptiveError::new(
-                    endpoint,
+                    self.transport.url(endpoint),
                     status,
                     error_info.map(|s| s + &error_text).unwrap_or(error_text),
                 ))
@@ -82,20 +193,50 @@ impl Client {
         }
     }
 
+    /// Once an explore runs past the usual latency for its size a duplicate is sent,
+    /// whichever succeeds first is taken.
     pub async fn explore(&self, area: &Area) -> ClientResponse<Explore> {
-        self.call(
-            &self.explore_url,
-            area,
-            |_, elapsed| RecordExplore {
-                area_size: area.size(),
-                duration: elapsed,
-                status: None,
+        let delay = match &self.hedging {
+            Some(hedging) => hedging.delay(size_bucket(area.size())),
+            None => None,
+        };
+        let delay = match delay {
+            Some(delay) => delay,
+            None => return self.explore_once(area).await,
+        };
+
+        let first = self.explore_once(area);
+        tokio::pin!(first);
+        tokio::select! {
+            res = &mut first => return res,
+            _ = tokio::time::sleep(delay) => (),
+        }
+        let hedge = self.explore_once(area);
+        tokio::pin!(hedge);
+        let (res, won) = tokio::select! {
+            res = &mut first => match res {
+                Ok(res) => (Ok(res), false),
+                Err(_) => {
+                    let res = hedge.await;
+                    let won = res.is_ok();
+                    (res, won)
+                }
             },
-            |status, elapsed| RecordExplore {
-                area_size: area.size(),
-                duration: elapsed,
-                status,
+            res = &mut hedge => match res {
+                Ok(res) => (Ok(res), true),
+                Err(_) => (first.await, false),
             },
+        };
+        self.metrics.record_hedge(won);
+        res
+    }
+
+    async fn explore_once(&self, area: &Area) -> ClientResponse<Explore> {
+        self.call(
+            Endpoint::Explore,
+            area,
+            |metrics, _, elapsed| metrics.record_explore(area.size(), elapsed, None),
+            |metrics, status, elapsed| metrics.record_explore(area.size(), elapsed, status),
             None,
         )
         .await
@@ -104,20 +245,12 @@ impl Client {
     pub async fn get_license(&self, coins: &Vec<u64>) -> ClientResponse<License> {
         let l = coins.len() as u64;
         self.call(
-            &self.licenses_url,
+            Endpoint::License,
             &coins,
-            |lic: &License, elapsed| RecordLicense {
-                duration: elapsed,
-                coins: l,
-                allowed: lic.dig_allowed,
-                status: None,
-            },
-            |status, elapsed| RecordLicense {
-                duration: elapsed,
-                coins: l,
-                allowed: 0,
-                status,
+            |metrics, lic: &License, elapsed| {
+                metrics.record_license(elapsed, l, lic.dig_allowed, None)
             },
+            |metrics, status, elapsed| metrics.record_license(elapsed, l, 0, status),
             None,
         )
         .await
@@ -125,24 +258,10 @@ impl Client {
 
     pub async fn dig(&self, dig: &Dig) -> ClientResponse<Vec<String>> {
         self.call(
-            &self.dig_url,
+            Endpoint::Dig,
             dig,
-            |_, elapsed| RecordDig {
-                depth: dig.depth,
-                x: dig.pos_x,
-                y: dig.pos_y,
-                found: true,
-                duration: elapsed,
-                status: None,
-            },
-            |status, elapsed| RecordDig {
-                depth: dig.depth,
-                x: dig.pos_x,
-                y: dig.pos_y,
-                found: false,
-                duration: elapsed,
-                status,
-            },
+            |metrics, _, elapsed| metrics.record_dig(dig.depth, elapsed, true, None),
+            |metrics, status, elapsed| metrics.record_dig(dig.depth, elapsed, false, status),
             Some(format!("{} {} {}", dig.pos_x, dig.pos_y, dig.depth)),
         )
         .await
@@ -150,20 +269,12 @@ impl Client {
 
     pub async fn cash(&self, t: &Treasure) -> ClientResponse<Vec<u64>> {
         self.call(
-            &self.cash_url,
+            Endpoint::Cash,
             &t.treasure,
-            |coins: &Vec<u64>, elapsed| RecordCash {
-                amount: coins.len() as u64,
-                depth: t.depth,
-                duration: elapsed,
-                status: None,
-            },
-            |status, elapsed| RecordCash {
-                amount: 0,
-                depth: t.depth,
-                duration: elapsed,
-                status,
+            |metrics, coins: &Vec<u64>, elapsed| {
+                metrics.record_cash(t.depth, coins.len() as u64, elapsed, None)
             },
+            |metrics, status, elapsed| metrics.record_cash(t.depth, 0, elapsed, status),
             None,
         )
         .await
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::http::scheduler::Endpoint;
+
+// a license or a cash given up on may still have been paid for or cashed,
+// they are only cut short when a deadline is set for them
+const UNBOUNDED: [Endpoint; 2] = [Endpoint::License, Endpoint::Cash];
+
+/// Time a request to each endpoint gets before it is given up on.
+#[derive(Debug, Clone)]
+pub struct Deadlines {
+    per_endpoint: HashMap<Endpoint, Duration>,
+}
+
+impl Deadlines {
+    /// `default` for every endpoint but licenses and cash, which wait for their answer.
+    pub fn new(default: Duration) -> Self {
+        Self {
+            per_endpoint: Endpoint::ALL
+                .iter()
+                .filter(|e| !UNBOUNDED.contains(e))
+                .map(|e| (*e, default))
+                .collect(),
+        }
+    }
+
+    /// Parses a comma separated list of `endpoint=milliseconds`,
+    /// endpoints left out keep the `default`.
+    pub fn parse(list: &str, default: Duration) -> Option<Self> {
+        let mut deadlines = Self::new(default);
+        for entry in list.split(',') {
+            let mut parts = entry.splitn(2, '=');
+            let endpoint = Endpoint::from_name(parts.next()?.trim())?;
+            let ms = parts.next()?.trim().parse::<u64>().ok()?;
+            deadlines
+                .per_endpoint
+                .insert(endpoint, Duration::from_millis(ms));
+        }
+        Some(deadlines)
+    }
+
+    pub fn get(&self, endpoint: Endpoint) -> Option<Duration> {
+        self.per_endpoint.get(&endpoint).copied()
+    }
+}
@@ -1,10 +1,6 @@
-use crate::constants::{AVG_DIG_MS, TIME_LIMIT_MS};
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
-use std::time::Instant;
 
-#[cfg_attr(test, derive(Clone))]
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Area {
     pub pos_x: u64,
@@ -22,6 +18,19 @@ impl Area {
         self.divide().into_iter().flat_map(|a| a.divide()).collect()
     }
 
+    /// Divides `levels` times over, a quadtree level each time.
+    pub fn split_levels(self, levels: u32) -> Vec<Area> {
+        (0..levels).fold(vec![self], |parts, _| {
+            parts.into_iter().flat_map(Area::divide).collect()
+        })
+    }
+
+    pub fn cells(&self) -> Vec<(u64, u64)> {
+        (self.pos_x..self.pos_x + self.size_x)
+            .flat_map(|x| (self.pos_y..self.pos_y + self.size_y).map(move |y| (x, y)))
+            .collect()
+    }
+
     pub fn size(&self) -> u64 {
         self.size_x * self.size_y
     }
@@ -87,32 +96,6 @@ pub struct Explore {
     pub amount: u64,
 }
 
-impl Explore {
-    pub fn cost(&self, max_depth: u8) -> u128 {
-        // todo: constants are no good here
-        self.area.size() as u128 * (max_depth as u128 / 3) * AVG_DIG_MS
-    }
-
-    pub fn is_managable(&self, started: Instant, max_depth: u8) -> bool {
-        let time_since_started_ms = started.elapsed().as_millis();
-        let remaining_time_ms = TIME_LIMIT_MS - time_since_started_ms;
-        self.cost(max_depth) < remaining_time_ms
-    }
-}
-
-impl Ord for Explore {
-    fn cmp(&self, other: &Self) -> Ordering {
-        (self.amount / self.area.size()).cmp(&(other.amount / other.area.size()))
-        // .then(self.area.size().cmp(&other.area.size()).reverse())
-    }
-}
-
-impl PartialOrd for Explore {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 #[derive(Debug, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct License {
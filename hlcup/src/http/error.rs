@@ -1,26 +1,69 @@
-use reqwest::Error;
+use std::time::Duration;
+
+use reqwest::{Error, StatusCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Transport,
+    Timeout,
+    Status(StatusCode),
+    /// the endpoint kept failing, it is not called again for `retry_in`
+    CircuitOpen { retry_in: Duration },
+}
 
 #[derive(Debug)]
 pub struct DescriptiveError {
+    pub kind: ErrorKind,
     pub message: String,
 }
 
 impl DescriptiveError {
     pub fn new(endpoint: &str, status_code: reqwest::StatusCode, message: String) -> DescriptiveError {
         DescriptiveError {
+            kind: ErrorKind::Status(status_code),
             message: format!("{} /{}: {}", status_code, endpoint, message),
         }
     }
+
+    pub fn circuit_open(endpoint: &str, retry_in: Duration) -> DescriptiveError {
+        DescriptiveError {
+            kind: ErrorKind::CircuitOpen { retry_in },
+            message: format!("circuit open /{} for {:?}", endpoint, retry_in),
+        }
+    }
 }
 
 impl std::convert::From<Error> for DescriptiveError {
     fn from(e: Error) -> Self {
         DescriptiveError {
+            kind: if e.is_timeout() {
+                ErrorKind::Timeout
+            } else {
+                ErrorKind::Transport
+            },
+            message: format!("{}", e),
+        }
+    }
+}
+
+impl std::convert::From<std::io::Error> for DescriptiveError {
+    fn from(e: std::io::Error) -> Self {
+        DescriptiveError {
+            kind: ErrorKind::Transport,
             message: format!("{}", e),
         }
     }
 }
 
+impl std::convert::From<serde_json::Error> for DescriptiveError {
+    fn from(e: serde_json::Error) -> Self {
+        DescriptiveError {
+            kind: ErrorKind::Transport,
+            message: format!("malformed json: {}", e),
+        }
+    }
+}
+
 impl std::fmt::Display for DescriptiveError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "err: {}", &self.message)
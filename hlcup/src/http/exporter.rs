@@ -0,0 +1,159 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+use crate::http::scheduler::Endpoint;
+use crate::models::metrics::{OriginStats, Registry, Snapshot};
+
+const MAX_REQUEST_HEAD: usize = 8192;
+// the same buckets in every scrape so that quantiles can be taken over time
+const LATENCY_BOUNDS_US: [u64; 16] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000,
+];
+
+// metric name, help and the per-origin value it exposes
+type OriginCounter = (&'static str, &'static str, fn(&OriginStats) -> u64);
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Renders a snapshot in the Prometheus text exposition format.
+pub fn render(snapshot: &Snapshot) -> String {
+    let mut out = String::new();
+
+    header(&mut out, "hlcup_requests_total", "counter", "Requests answered per endpoint.");
+    for endpoint in Endpoint::ALL.iter() {
+        let _ = writeln!(
+            out,
+            "hlcup_requests_total{{endpoint=\"{}\"}} {}",
+            endpoint.name(),
+            snapshot.endpoint(*endpoint).total()
+        );
+    }
+
+    header(&mut out, "hlcup_request_errors_total", "counter", "Error responses per endpoint and status.");
+    for endpoint in Endpoint::ALL.iter() {
+        for (status, count) in snapshot.endpoint(*endpoint).statuses.iter() {
+            let _ = writeln!(
+                out,
+                "hlcup_request_errors_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+                endpoint.name(),
+                status,
+                count
+            );
+        }
+    }
+
+    // keys are log2 size buckets for explores and depths for digs and cashes
+    let name = "hlcup_request_duration_microseconds";
+    header(&mut out, name, "histogram", "Request latency per endpoint and histogram key.");
+    for endpoint in Endpoint::ALL.iter() {
+        for (key, histogram) in snapshot.endpoint(*endpoint).histograms.iter() {
+            let labels = format!("endpoint=\"{}\",key=\"{}\"", endpoint.name(), key);
+            for (bound, count) in histogram.cumulative(&LATENCY_BOUNDS_US) {
+                let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, count);
+            }
+            let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, histogram.entries());
+            let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, histogram.sum());
+            let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, histogram.entries());
+        }
+    }
+
+    header(&mut out, "hlcup_dig_found_rate", "gauge", "Share of digs that found treasure per depth.");
+    for (depth, (digs, found)) in snapshot.dig_found_per_depth.iter() {
+        let _ = writeln!(out, "hlcup_dig_found_rate{{depth=\"{}\"}} {}", depth, found / digs);
+    }
+
+    header(&mut out, "hlcup_licenses_total", "counter", "Licenses bought per number of coins paid.");
+    for (coins, count) in snapshot.licenses_per_coins.iter() {
+        let _ = writeln!(out, "hlcup_licenses_total{{coins=\"{}\"}} {}", coins, count);
+    }
+
+    let per_origin: [OriginCounter; 5] = [
+        ("hlcup_origin_requests_total", "Requests answered per actor.", |o| o.requests),
+        ("hlcup_origin_errors_total", "Error responses per actor.", |o| o.errors),
+        ("hlcup_origin_timeouts_total", "Timed out requests per actor.", |o| o.timeouts),
+        ("hlcup_origin_digs_total", "Digs answered per actor.", |o| o.digs),
+        ("hlcup_origin_found_total", "Digs that found treasure per actor.", |o| o.found),
+    ];
+    for (name, help, value) in per_origin.iter() {
+        header(&mut out, name, "counter", help);
+        for (origin, stats) in snapshot.origins.iter() {
+            let _ = writeln!(out, "{}{{origin=\"{}\"}} {}", name, origin, value(stats));
+        }
+    }
+
+    let name = "hlcup_origin_time_microseconds_total";
+    header(&mut out, name, "counter", "Wall-clock time per actor and activity.");
+    for (origin, stats) in snapshot.origins.iter() {
+        for (activity, us) in stats.times.spent_us.iter() {
+            let _ = writeln!(
+                out,
+                "{}{{origin=\"{}\",activity=\"{}\"}} {}",
+                name,
+                origin,
+                activity.name(),
+                us
+            );
+        }
+    }
+
+    header(&mut out, "hlcup_coins", "gauge", "Coins held by accounting.");
+    let _ = writeln!(out, "hlcup_coins {}", snapshot.coins);
+    header(&mut out, "hlcup_active_licenses", "gauge", "Licenses not used up yet.");
+    let _ = writeln!(out, "hlcup_active_licenses {}", snapshot.active_licenses);
+    out
+}
+
+async fn respond(mut stream: TcpStream, registry: &Registry) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+            return Ok(());
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+
+    let (status, body) = if head.starts_with(b"GET /metrics ") {
+        ("200 OK", render(&registry.snapshot()))
+    } else {
+        ("404 Not Found", String::new())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Serves `GET /metrics` until the process exits, one connection per scrape.
+pub async fn serve(address: String, registry: Arc<Registry>) {
+    let listener = TcpListener::bind(&address)
+        .await
+        .expect("failed to bind METRICS_ADDR");
+    info!("Metrics at http://{}/metrics", address);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = respond(stream, &registry).await {
+                        warn!("metrics scrape failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("metrics accept failed: {}", e),
+        }
+    }
+}
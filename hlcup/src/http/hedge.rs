@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::constants::{HEDGE_PERCENTILE, PLANNER_REFRESH_MS};
+use crate::http::scheduler::Endpoint;
+use crate::models::metrics::Registry;
+
+struct Delays {
+    refreshed: Option<Instant>,
+    // percentile latency per histogram key of the endpoint
+    per_key_us: BTreeMap<u8, u64>,
+}
+
+/// Tells when a duplicate of a slow request should be sent,
+/// using latency percentiles merged from the metrics registry.
+pub struct Hedging {
+    endpoint: Endpoint,
+    delays: Mutex<Delays>,
+    registry: Arc<Registry>,
+}
+
+impl Hedging {
+    pub fn new(endpoint: Endpoint, registry: Arc<Registry>) -> Self {
+        Self {
+            endpoint,
+            delays: Mutex::new(Delays {
+                refreshed: None,
+                per_key_us: BTreeMap::new(),
+            }),
+            registry,
+        }
+    }
+
+    /// `None` until requests with the same key were measured.
+    pub fn delay(&self, key: u8) -> Option<Duration> {
+        let mut delays = self.delays.lock().expect("hedging lock poisoned");
+        let stale = delays
+            .refreshed
+            .is_none_or(|r| r.elapsed().as_millis() >= PLANNER_REFRESH_MS);
+        if stale {
+            delays.per_key_us = self
+                .registry
+                .endpoint(self.endpoint)
+                .percentiles(HEDGE_PERCENTILE);
+            delays.refreshed = Some(Instant::now());
+        }
+        delays.per_key_us.get(&key).copied().map(Duration::from_micros)
+    }
+}
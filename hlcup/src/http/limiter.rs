@@ -0,0 +1,167 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use reqwest::StatusCode;
+use tokio::sync::Notify;
+
+use crate::http::scheduler::Endpoint;
+
+const MIN_WINDOW: f64 = 1.;
+const MAX_WINDOW: f64 = 256.;
+// window is cut by these factors on overload statuses and on slow responses
+const OVERLOAD_BACKOFF: f64 = 0.5;
+const LATENCY_BACKOFF: f64 = 0.8;
+// p90 latency above this many times the healthy one counts as overload
+const LATENCY_TOLERANCE: f64 = 2.;
+const LATENCY_SAMPLES: usize = 32;
+
+struct Window {
+    limit: f64,
+    in_flight: usize,
+    recent_us: VecDeque<u64>,
+    // p90 while the server was healthy, follows it up slowly and down at once
+    baseline_us: Option<f64>,
+    // responses since the last cut, the window is cut at most once per round trip
+    since_cut: usize,
+}
+
+impl Window {
+    fn p90(&self) -> Option<f64> {
+        if self.recent_us.len() < LATENCY_SAMPLES {
+            return None;
+        }
+        let mut sorted = self.recent_us.iter().copied().collect::<Vec<u64>>();
+        sorted.sort_unstable();
+        Some(sorted[LATENCY_SAMPLES * 9 / 10] as f64)
+    }
+
+    fn adjust(&mut self, duration_us: u64, status: Option<StatusCode>) {
+        self.since_cut += 1;
+        match status {
+            None | Some(StatusCode::TOO_MANY_REQUESTS) | Some(StatusCode::SERVICE_UNAVAILABLE) => {
+                self.cut(OVERLOAD_BACKOFF);
+                return;
+            }
+            _ => (),
+        }
+
+        if self.recent_us.len() == LATENCY_SAMPLES {
+            self.recent_us.pop_front();
+        }
+        self.recent_us.push_back(duration_us);
+        let p90 = self.p90();
+        match (p90, self.baseline_us) {
+            (Some(p90), Some(baseline)) if p90 > baseline * LATENCY_TOLERANCE => {
+                self.cut(LATENCY_BACKOFF);
+            }
+            (p90, baseline) => {
+                if let Some(p90) = p90 {
+                    let baseline = baseline.unwrap_or(p90);
+                    self.baseline_us = Some(p90.min(baseline + (p90 - baseline) / 100.));
+                }
+                // a window that is not filled up says nothing about a larger one
+                if self.in_flight >= self.limit as usize {
+                    self.limit = (self.limit + 1. / self.limit).min(MAX_WINDOW);
+                }
+            }
+        }
+    }
+
+    fn cut(&mut self, factor: f64) {
+        if self.since_cut as f64 >= self.limit {
+            self.limit = (self.limit * factor).max(MIN_WINDOW);
+            self.since_cut = 0;
+        }
+    }
+}
+
+/// AIMD limit on requests in flight to one endpoint. The window grows by one
+/// every round trip while it is full and the server is healthy and is cut when it is overloaded.
+pub struct Limiter {
+    window: Mutex<Window>,
+    released: Notify,
+}
+
+pub struct LimiterPermit<'a> {
+    limiter: &'a Limiter,
+}
+
+impl Drop for LimiterPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.lock().in_flight -= 1;
+        self.limiter.released.notify_waiters();
+    }
+}
+
+impl Limiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            window: Mutex::new(Window {
+                limit: (limit as f64).clamp(MIN_WINDOW, MAX_WINDOW),
+                in_flight: 0,
+                recent_us: VecDeque::with_capacity(LATENCY_SAMPLES),
+                baseline_us: None,
+                since_cut: 0,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Window> {
+        self.window.lock().expect("limiter lock poisoned")
+    }
+
+    pub async fn acquire(&self) -> LimiterPermit<'_> {
+        loop {
+            // created before the check so a release in between is not missed
+            let released = self.released.notified();
+            {
+                let mut window = self.lock();
+                if window.in_flight < window.limit as usize {
+                    window.in_flight += 1;
+                    return LimiterPermit { limiter: self };
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Adjusts the window given how a request went, `status` is `None` when it never
+    /// got a response. Called while the request's permit is still held.
+    /// Returns the new limit when it changed.
+    pub fn record(&self, duration_us: u64, status: Option<StatusCode>) -> Option<usize> {
+        let mut window = self.lock();
+        let before = window.limit as usize;
+        window.adjust(duration_us, status);
+        let after = window.limit as usize;
+        drop(window);
+        if after > before {
+            self.released.notify_waiters();
+        }
+        if after != before {
+            Some(after)
+        } else {
+            None
+        }
+    }
+}
+
+/// One limiter per endpoint, all starting with the same window.
+pub struct RateLimits {
+    limiters: HashMap<Endpoint, Limiter>,
+}
+
+impl RateLimits {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limiters: Endpoint::ALL
+                .iter()
+                .map(|endpoint| (*endpoint, Limiter::new(limit)))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, endpoint: Endpoint) -> &Limiter {
+        &self.limiters[&endpoint]
+    }
+}
@@ -1,3 +1,10 @@
+pub mod breaker;
 pub mod client;
+pub mod deadlines;
 pub mod dto;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod exporter;
+pub mod hedge;
+pub mod limiter;
+pub mod scheduler;
+pub mod transport;
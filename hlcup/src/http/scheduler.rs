@@ -0,0 +1,183 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Endpoint {
+    Cash,
+    License,
+    Dig,
+    Explore,
+}
+
+impl Endpoint {
+    pub const ALL: [Endpoint; 4] = [
+        Endpoint::Cash,
+        Endpoint::License,
+        Endpoint::Dig,
+        Endpoint::Explore,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endpoint::Cash => "cash",
+            Endpoint::License => "license",
+            Endpoint::Dig => "dig",
+            Endpoint::Explore => "explore",
+        }
+    }
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            Endpoint::Cash => "/cash",
+            Endpoint::License => "/licenses",
+            Endpoint::Dig => "/dig",
+            Endpoint::Explore => "/explore",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Endpoint> {
+        Endpoint::ALL.iter().copied().find(|e| e.name() == name)
+    }
+}
+
+/// Parses a comma separated list of endpoints, the first one goes first.
+/// Endpoints left out are served last, in the default order.
+pub fn parse_priorities(list: &str) -> Option<Vec<Endpoint>> {
+    let mut order = list
+        .split(',')
+        .map(|name| Endpoint::from_name(name.trim()))
+        .collect::<Option<Vec<Endpoint>>>()?;
+    for endpoint in Endpoint::ALL {
+        if !order.contains(&endpoint) {
+            order.push(endpoint);
+        }
+    }
+    Some(order)
+}
+
+struct Waiter {
+    rank: usize,
+    seq: u64,
+    tx: oneshot::Sender<Permit>,
+}
+
+// lower rank first, then first come first served
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.rank.cmp(&self.rank).then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+struct Queue {
+    in_flight: usize,
+    seq: u64,
+    waiting: BinaryHeap<Waiter>,
+}
+
+/// Dispatcher every request goes through. At most `max_in_flight` requests
+/// are sent at once, the rest wait and are let through by endpoint priority.
+pub struct Scheduler {
+    max_in_flight: usize,
+    priorities: Vec<Endpoint>,
+    queue: Mutex<Queue>,
+}
+
+/// Slot for a single request, handed over to the next waiter when dropped.
+pub struct Permit {
+    scheduler: Option<Arc<Scheduler>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            Scheduler::release(scheduler);
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(max_in_flight: usize, priorities: Vec<Endpoint>) -> Arc<Self> {
+        Arc::new(Self {
+            max_in_flight: max_in_flight.max(1),
+            priorities,
+            queue: Mutex::new(Queue {
+                in_flight: 0,
+                seq: 0,
+                waiting: BinaryHeap::new(),
+            }),
+        })
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    fn rank(&self, endpoint: Endpoint) -> usize {
+        self.priorities
+            .iter()
+            .position(|e| *e == endpoint)
+            .unwrap_or(self.priorities.len())
+    }
+
+    pub async fn acquire(self: &Arc<Self>, endpoint: Endpoint) -> Permit {
+        let rx = {
+            let mut queue = self.queue.lock().expect("scheduler lock poisoned");
+            if queue.in_flight < self.max_in_flight && queue.waiting.is_empty() {
+                queue.in_flight += 1;
+                return Permit {
+                    scheduler: Some(self.clone()),
+                };
+            }
+            let (tx, rx) = oneshot::channel();
+            queue.seq += 1;
+            let waiter = Waiter {
+                rank: self.rank(endpoint),
+                seq: queue.seq,
+                tx,
+            };
+            queue.waiting.push(waiter);
+            rx
+        };
+        // the sender is only dropped together with the scheduler, which outlives this call
+        rx.await.expect("scheduler dropped a waiter")
+    }
+
+    // a permit not received by a cancelled waiter comes back here on drop
+    fn release(scheduler: Arc<Scheduler>) {
+        let mut queue = scheduler.queue.lock().expect("scheduler lock poisoned");
+        while let Some(waiter) = queue.waiting.pop() {
+            let permit = Permit {
+                scheduler: Some(scheduler.clone()),
+            };
+            match waiter.tx.send(permit) {
+                Ok(()) => return,
+                // waiter went away, its permit must not release the slot again
+                Err(mut permit) => {
+                    permit.scheduler = None;
+                }
+            }
+        }
+        queue.in_flight -= 1;
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(not(feature = "raw-http"))]
+mod pooled;
+#[cfg(feature = "raw-http")]
+mod raw;
+
+#[cfg(not(feature = "raw-http"))]
+pub use pooled::Transport;
+#[cfg(feature = "raw-http")]
+pub use raw::Transport;
+
+use std::collections::HashMap;
+
+use reqwest::StatusCode;
+
+use crate::http::scheduler::Endpoint;
+
+/// Status and body as they came from the server, the body is borrowed
+/// from the transport's buffer while `Client` parses it.
+#[derive(Debug)]
+pub struct RawResponse<'a> {
+    pub status: StatusCode,
+    pub body: &'a [u8],
+}
+
+fn urls(authority: &str) -> HashMap<Endpoint, String> {
+    Endpoint::ALL
+        .iter()
+        .map(|e| (*e, format!("http://{}{}", authority, e.path())))
+        .collect()
+}
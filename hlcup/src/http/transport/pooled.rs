@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::http::client::ClientResponse;
+use crate::http::scheduler::Endpoint;
+use crate::http::transport::{urls, RawResponse};
+
+/// reqwest with its connection pool shared by every clone.
+#[derive(Clone)]
+pub struct Transport {
+    client: reqwest::Client,
+    urls: Arc<HashMap<Endpoint, String>>,
+}
+
+impl Transport {
+    pub fn new(authority: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            urls: Arc::new(urls(authority)),
+        }
+    }
+
+    /// Connections are pooled across workers anyway.
+    pub fn detached(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn url(&self, endpoint: Endpoint) -> &str {
+        &self.urls[&endpoint]
+    }
+
+    pub async fn post<Payload: Serialize, T>(
+        &self,
+        endpoint: Endpoint,
+        payload: &Payload,
+        timeout: Option<Duration>,
+        read: impl FnOnce(RawResponse<'_>) -> T,
+    ) -> ClientResponse<T> {
+        let mut request = self.client.post(self.url(endpoint));
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.json(payload).send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        Ok(read(RawResponse { status, body: &body }))
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::http::client::ClientResponse;
+use crate::http::error::{DescriptiveError, ErrorKind};
+use crate::http::scheduler::Endpoint;
+use crate::http::transport::{urls, RawResponse};
+
+const MAX_HEADERS: usize = 16;
+// room left for the content length, padded with spaces the header value may start with
+const LENGTH_DIGITS: usize = 10;
+const READ_CHUNK: usize = 4096;
+
+struct Connection {
+    stream: TcpStream,
+    buf: Vec<u8>,
+}
+
+impl Connection {
+    async fn open(authority: &str) -> io::Result<Connection> {
+        let stream = TcpStream::connect(authority).await?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            buf: Vec::with_capacity(READ_CHUNK),
+        })
+    }
+
+    /// Sends a request and reads the whole response, the body stays in the buffer
+    /// at the returned range. The flag tells whether the connection can be used again.
+    async fn exchange<Payload: Serialize>(
+        &mut self,
+        head: &[u8],
+        payload: &Payload,
+    ) -> io::Result<(StatusCode, Range<usize>, bool)> {
+        self.buf.clear();
+        self.buf.extend_from_slice(head);
+        let length_at = self.buf.len();
+        self.buf.extend_from_slice(&[b' '; LENGTH_DIGITS]);
+        self.buf.extend_from_slice(b"\r\n\r\n");
+        let body_at = self.buf.len();
+        serde_json::to_writer(&mut self.buf, payload)?;
+        let length = self.buf.len() - body_at;
+        let mut length_field = &mut self.buf[length_at..length_at + LENGTH_DIGITS];
+        write!(length_field, "{:>width$}", length, width = LENGTH_DIGITS)?;
+        self.stream.write_all(&self.buf).await?;
+
+        self.buf.clear();
+        let mut chunk = [0u8; READ_CHUNK];
+        let (head_len, status, content_length, keep_alive) = loop {
+            let read = self.stream.read(&mut chunk).await?;
+            if read == 0 {
+                let kind = if self.buf.is_empty() {
+                    io::ErrorKind::UnexpectedEof
+                } else {
+                    io::ErrorKind::InvalidData
+                };
+                return Err(io::Error::new(kind, "connection closed before response head"));
+            }
+            self.buf.extend_from_slice(&chunk[..read]);
+
+            let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+            let mut response = httparse::Response::new(&mut headers);
+            let parsed = response
+                .parse(&self.buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if let httparse::Status::Complete(head_len) = parsed {
+                let status = StatusCode::from_u16(response.code.unwrap_or(0))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                let mut content_length = None;
+                let mut keep_alive = true;
+                for header in response.headers.iter() {
+                    let value = String::from_utf8_lossy(header.value);
+                    if header.name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse::<usize>().ok();
+                    } else if header.name.eq_ignore_ascii_case("connection") {
+                        keep_alive = !value.eq_ignore_ascii_case("close");
+                    } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "chunked responses are not supported",
+                        ));
+                    }
+                }
+                break (head_len, status, content_length, keep_alive);
+            }
+        };
+
+        // without a length the body ends with the connection
+        let (body_end, keep_alive) = match content_length {
+            Some(length) => (Some(head_len + length), keep_alive),
+            None => (None, false),
+        };
+        while body_end.is_none_or(|end| self.buf.len() < end) {
+            let read = self.stream.read(&mut chunk).await?;
+            if read == 0 {
+                if body_end.is_some() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated body"));
+                }
+                break;
+            }
+            self.buf.extend_from_slice(&chunk[..read]);
+        }
+        let body_end = body_end.unwrap_or(self.buf.len());
+        Ok((status, head_len..body_end, keep_alive))
+    }
+}
+
+/// HTTP/1.1 over plain TCP with keep-alive connections. Request heads are built
+/// once per endpoint and payloads are serialized straight into the send buffer.
+/// Clones share idle connections, `detached` gives a worker its own.
+#[derive(Clone)]
+pub struct Transport {
+    authority: Arc<String>,
+    urls: Arc<HashMap<Endpoint, String>>,
+    // request line and headers up to the content length value
+    heads: Arc<HashMap<Endpoint, Vec<u8>>>,
+    idle: Arc<Mutex<Vec<Connection>>>,
+}
+
+impl Transport {
+    pub fn new(authority: &str) -> Self {
+        let heads = Endpoint::ALL
+            .iter()
+            .map(|e| {
+                let head = format!(
+                    "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: ",
+                    e.path(),
+                    authority
+                );
+                (*e, head.into_bytes())
+            })
+            .collect();
+        Self {
+            authority: Arc::new(authority.to_string()),
+            urls: Arc::new(urls(authority)),
+            heads: Arc::new(heads),
+            idle: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn detached(&self) -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(vec![])),
+            ..self.clone()
+        }
+    }
+
+    pub fn url(&self, endpoint: Endpoint) -> &str {
+        &self.urls[&endpoint]
+    }
+
+    fn idle(&self) -> std::sync::MutexGuard<'_, Vec<Connection>> {
+        self.idle.lock().expect("connection pool lock poisoned")
+    }
+
+    async fn exchange<Payload: Serialize, T>(
+        &self,
+        head: &[u8],
+        payload: &Payload,
+        read: impl FnOnce(RawResponse<'_>) -> T,
+    ) -> io::Result<T> {
+        let reused = self.idle().pop();
+        let was_reused = reused.is_some();
+        let mut conn = match reused {
+            Some(conn) => conn,
+            None => Connection::open(&self.authority).await?,
+        };
+        let result = match conn.exchange(head, payload).await {
+            // the server may have closed an idle connection, nothing was processed then
+            Err(e) if was_reused && closed(&e) => {
+                conn = Connection::open(&self.authority).await?;
+                conn.exchange(head, payload).await
+            }
+            result => result,
+        };
+        let (status, body, keep_alive) = result?;
+        let response = read(RawResponse { status, body: &conn.buf[body] });
+        if keep_alive {
+            self.idle().push(conn);
+        }
+        Ok(response)
+    }
+
+    pub async fn post<Payload: Serialize, T>(
+        &self,
+        endpoint: Endpoint,
+        payload: &Payload,
+        timeout: Option<Duration>,
+        read: impl FnOnce(RawResponse<'_>) -> T,
+    ) -> ClientResponse<T> {
+        let exchange = self.exchange(&self.heads[&endpoint], payload, read);
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return Ok(exchange.await?),
+        };
+        match tokio::time::timeout(timeout, exchange).await {
+            Ok(response) => Ok(response?),
+            Err(_) => Err(DescriptiveError {
+                kind: ErrorKind::Timeout,
+                message: format!("/{}: no response in {:?}", self.url(endpoint), timeout),
+            }),
+        }
+    }
+}
+
+// how a connection the server dropped while idle fails on its next use
+fn closed(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::UnexpectedEof | io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe
+    )
+}
@@ -2,22 +2,31 @@ mod http;
 mod actors;
 mod constants;
 mod models;
+mod strategy;
 
 #[cfg(test)]
 mod tests;
 
 use futures::stream::FuturesUnordered;
 use futures::{Future, StreamExt};
-use std::time::Instant;
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, info_span, Instrument};
+use tracing_subscriber::EnvFilter;
 
 use crate::models::messages::{MessageForAccounting, StatsMessage};
 use crate::http::client::Client;
 use crate::http::dto::Area;
+use crate::http::deadlines::Deadlines;
+use crate::http::exporter;
+use crate::http::scheduler::{self, Scheduler};
+use crate::models::metrics::Registry;
 use crate::actors::accounting::Accounting;
-use crate::actors::stats::{StatsActor};
+use crate::actors::stats::{StatsActor, StatsFiles};
 use crate::actors::Handler;
 use crate::actors::worker::Worker;
+use crate::strategy::SharedStrategy;
 
 #[derive(Clone)]
 pub struct Rules {
@@ -26,22 +35,32 @@ pub struct Rules {
     pub n_workers: u64,
     max_concurrent_licenses: u8,
     pub max_depth: u8,
+    pub clustering: bool,
 }
 
 impl Rules {
-    pub fn new(n_workers: u64) -> Self {
-        Self { w: 3500 / n_workers, h: 3500, n_workers, max_concurrent_licenses: 10, max_depth: 10 }
+    pub fn new(n_workers: u64, clustering: bool) -> Self {
+        Self {
+            w: 3500 / n_workers,
+            h: 3500,
+            n_workers,
+            max_concurrent_licenses: 10,
+            max_depth: 10,
+            clustering,
+        }
     }
 }
 
 async fn task(
     client: Client,
     rules: Rules,
+    strategy: SharedStrategy,
     accounting_handle: mpsc::Sender<MessageForAccounting>,
+    stats_handler: mpsc::Sender<StatsMessage>,
     started: Instant,
     areas: Vec<Area>
 ) {
-    Worker::new(client, rules, started, areas, accounting_handle)
+    Worker::new(client, rules, strategy, started, areas, accounting_handle, stats_handler)
         .await
         .run()
         .await
@@ -49,39 +68,156 @@ async fn task(
 
 fn spawn_tasks(
     rules: Rules,
+    strategy: SharedStrategy,
     client: Client,
     accounting_handle: mpsc::Sender<MessageForAccounting>,
+    stats_handler: mpsc::Sender<StatsMessage>,
     started: Instant,
 ) -> FuturesUnordered<impl Future<Output = ()>> {
-    println!("Started threads = {}", rules.n_workers);
+    info!("Started threads = {}", rules.n_workers);
 
     (0..rules.n_workers)
         .map(|i| {
             let area = Area::initial_stripe(rules.w, rules.h, i);
-            task(client.clone(), rules.clone(), accounting_handle.clone(), started, area.split_in_8())
+            task(
+                client.for_worker(i),
+                rules.clone(),
+                strategy.clone(),
+                accounting_handle.clone(),
+                stats_handler.clone(),
+                started,
+                area.split_in_8(),
+            )
+            .instrument(info_span!("worker", index = i))
         })
         .collect::<FuturesUnordered<_>>()
 }
 
+async fn write_report(stats_handler: &mpsc::Sender<StatsMessage>) {
+    let (tx, rx) = oneshot::channel();
+    stats_handler
+        .send(StatsMessage::WriteReport(tx))
+        .await
+        .expect("failed to request stats report");
+    let _ = rx.await;
+}
+
+async fn report_periodically(stats_handler: mpsc::Sender<StatsMessage>, every: Duration) {
+    let mut interval = tokio::time::interval(every);
+    // the first tick completes right away
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        write_report(&stats_handler).await;
+    }
+}
+
+async fn sample_periodically(stats_handler: mpsc::Sender<StatsMessage>, every: Duration) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        stats_handler
+            .send(StatsMessage::Sample)
+            .await
+            .expect("failed to request a stats sample");
+    }
+}
+
+/// `SIGUSR1` shows the stats and `SIGUSR2` resets them, the game goes on either way.
+async fn handle_signals(stats_handler: mpsc::Sender<StatsMessage>) {
+    let mut show = signal(SignalKind::user_defined1()).expect("failed to listen to SIGUSR1");
+    let mut reset = signal(SignalKind::user_defined2()).expect("failed to listen to SIGUSR2");
+    loop {
+        let msg = tokio::select! {
+            _ = show.recv() => StatsMessage::ShowStats,
+            _ = reset.recv() => {
+                info!("Stats reset");
+                StatsMessage::ResetStats
+            }
+        };
+        stats_handler.send(msg).await.expect("failed to send signalled stats request");
+    }
+}
+
+/// `RUST_LOG` picks what is logged (`info` by default), `LOG_FORMAT` is one of `full`, `compact` or `json`.
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let format = std::env::var("LOG_FORMAT").unwrap_or_else(|_| "full".to_string());
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format.as_str() {
+        "json" => builder.json().init(),
+        "compact" => builder.compact().init(),
+        "full" => builder.init(),
+        other => panic!("unknown LOG_FORMAT {}", other),
+    }
+}
+
 #[tokio::main]
 async fn main() {
+    init_tracing();
     let n_workers = std::env::var("WORKERS")
         .expect("missing env variable WORKERS")
         .parse::<u64>()
         .expect("malformed WORKERS variable");
 
-    let rules = Rules::new(n_workers);
+    let strategy_name = std::env::var("STRATEGY").unwrap_or_else(|_| strategy::DEFAULT.to_string());
+    let strategy = strategy::from_name(&strategy_name).expect("unknown STRATEGY");
+    info!("Strategy = {}", strategy.name());
+
+    let clustering = std::env::var("CLUSTERING")
+        .map(|v| v != "off")
+        .unwrap_or(true);
+
+    let rules = Rules::new(n_workers, clustering);
     let started = Instant::now();
 
     let address = std::env::var("ADDRESS").expect("missing env variable ADDRESS");
-    let stats_hanlder = Handler::new(StatsActor::new);
-    let client = Client::new(&address, stats_hanlder.tx.clone());
+    let registry = Registry::new();
+    let stats_registry = registry.clone();
+    let files = StatsFiles {
+        report: std::env::var("STATS_FILE").ok(),
+        timeline: std::env::var("TIMELINE_FILE").ok(),
+        map_dir: std::env::var("MAP_DIR").ok(),
+    };
+    let report_every = std::env::var("STATS_INTERVAL_S")
+        .map(|v| v.parse::<u64>().expect("malformed STATS_INTERVAL_S variable"))
+        .unwrap_or(constants::REPORT_INTERVAL_S);
+    let periodic_reports = files.any();
+    let stats_hanlder =
+        Handler::new(|rx| StatsActor::new(rx, stats_registry, files, started));
+    tokio::spawn(sample_periodically(
+        stats_hanlder.tx.clone(),
+        Duration::from_millis(constants::TIMELINE_WINDOW_MS),
+    ));
+    tokio::spawn(handle_signals(stats_hanlder.tx.clone()));
+    if periodic_reports {
+        tokio::spawn(report_periodically(
+            stats_hanlder.tx.clone(),
+            Duration::from_secs(report_every),
+        ));
+    }
+    let max_in_flight = std::env::var("MAX_IN_FLIGHT")
+        .map(|v| v.parse::<usize>().expect("malformed MAX_IN_FLIGHT variable"))
+        .unwrap_or(n_workers as usize);
+    let priorities = std::env::var("PRIORITIES")
+        .map(|v| scheduler::parse_priorities(&v).expect("unknown endpoint in PRIORITIES"))
+        .unwrap_or_else(|_| scheduler::Endpoint::ALL.to_vec());
+    let scheduler = Scheduler::new(max_in_flight, priorities);
+    let default_timeout = Duration::from_millis(constants::DEFAULT_TIMEOUT_MS);
+    let deadlines = std::env::var("TIMEOUTS")
+        .map(|v| Deadlines::parse(&v, default_timeout).expect("malformed TIMEOUTS variable"))
+        .unwrap_or_else(|_| Deadlines::new(default_timeout));
+    let hedging = std::env::var("HEDGING").map(|v| v == "on").unwrap_or(false);
+    if let Ok(metrics_address) = std::env::var("METRICS_ADDR") {
+        tokio::spawn(exporter::serve(metrics_address, registry.clone()));
+    }
+    let client = Client::new(&address, scheduler, deadlines, hedging, registry);
 
-    let mk_accounting = Accounting::new(&client, rules.max_concurrent_licenses);
+    let mk_accounting = Accounting::new(&client, strategy.clone(), rules.max_concurrent_licenses);
     let accounting_handle = Handler::new(mk_accounting);
 
     tokio::select! {
-        _ = spawn_tasks(rules, client, accounting_handle.tx, started).collect::<_>() => (),
+        _ = spawn_tasks(rules, strategy, client, accounting_handle.tx, stats_hanlder.tx.clone(), started).collect::<()>() => (),
         res = tokio::signal::ctrl_c() => {
             if res.is_ok() {
                 stats_hanlder.tx.send(StatsMessage::ShowStats).await
@@ -89,4 +225,11 @@ async fn main() {
             }
         }
     };
+    stats_hanlder.tx.send(StatsMessage::Sample).await
+        .expect("failed to request a stats sample");
+    write_report(&stats_hanlder.tx).await;
+    let (tx, rx) = oneshot::channel();
+    stats_hanlder.tx.send(StatsMessage::WriteMap(tx)).await
+        .expect("failed to request the field map");
+    let _ = rx.await;
 }
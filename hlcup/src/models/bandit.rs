@@ -0,0 +1,66 @@
+use crate::http::dto::Area;
+
+// weight of the exploration term in UCB1
+const UCB_EXPLORATION: f64 = 0.5;
+
+#[derive(Debug, Default, Clone)]
+struct Arm {
+    requests: f64,
+    coins: f64,
+}
+
+/// Coins per request observed in each of the top level regions of a worker,
+/// treated as arms of a multi-armed bandit.
+#[derive(Debug)]
+pub struct Bandit {
+    regions: Vec<Area>,
+    arms: Vec<Arm>,
+}
+
+impl Bandit {
+    pub fn new(regions: Vec<Area>) -> Self {
+        let arms = vec![Arm::default(); regions.len()];
+        Self { regions, arms }
+    }
+
+    pub fn regions(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Region containing the cell, the first one if it lies outside of all of them.
+    pub fn region_of(&self, x: u64, y: u64) -> usize {
+        self.regions
+            .iter()
+            .position(|r| {
+                (r.pos_x..r.pos_x + r.size_x).contains(&x)
+                    && (r.pos_y..r.pos_y + r.size_y).contains(&y)
+            })
+            .unwrap_or(0)
+    }
+
+    pub fn record_request(&mut self, region: usize) {
+        self.arms[region].requests += 1.;
+    }
+
+    pub fn record_coins(&mut self, region: usize, coins: f64) {
+        self.arms[region].coins += coins;
+    }
+
+    /// UCB1 score of the region, regions never tried come first.
+    pub fn ucb(&self, region: usize) -> f64 {
+        let arm = &self.arms[region];
+        if arm.requests == 0. {
+            return f64::INFINITY;
+        }
+        let total = self.arms.iter().map(|a| a.requests).sum::<f64>();
+        arm.coins / arm.requests + UCB_EXPLORATION * (2. * total.ln() / arm.requests).sqrt()
+    }
+
+    /// Region with the best score among `candidates`, ties go to the first one.
+    pub fn choose(&self, candidates: impl IntoIterator<Item = usize>) -> Option<usize> {
+        candidates.into_iter().fold(None, |best, region| match best {
+            Some(b) if self.ucb(b) >= self.ucb(region) => Some(b),
+            _ => Some(region),
+        })
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use crate::http::dto::Area;
+
+// hits closer than this many cells to an area count as its neighbours
+const CLUSTER_RADIUS: u64 = 16;
+
+/// Cells where digs found treasure, bucketed on a grid of `CLUSTER_RADIUS`.
+/// When disabled nothing is recorded and every area has no neighbouring hits.
+#[derive(Debug)]
+pub struct HitIndex {
+    enabled: bool,
+    buckets: HashMap<(u64, u64), u64>,
+}
+
+impl HitIndex {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, x: u64, y: u64, found: u64) {
+        if self.enabled {
+            *self
+                .buckets
+                .entry((x / CLUSTER_RADIUS, y / CLUSTER_RADIUS))
+                .or_insert(0) += found;
+        }
+    }
+
+    /// Treasures found in buckets touching the area grown by `CLUSTER_RADIUS` on every side.
+    pub fn neighbours(&self, area: &Area) -> u64 {
+        if self.buckets.is_empty() {
+            return 0;
+        }
+        let (min_x, min_y, max_x, max_y) = reach(area);
+        let within = |(bx, by): &(u64, u64)| (min_x..=max_x).contains(bx) && (min_y..=max_y).contains(by);
+
+        if (max_x - min_x + 1) * (max_y - min_y + 1) > self.buckets.len() as u64 {
+            self.buckets
+                .iter()
+                .filter(|(bucket, _)| within(bucket))
+                .map(|(_, found)| found)
+                .sum()
+        } else {
+            (min_x..=max_x)
+                .flat_map(|bx| (min_y..=max_y).map(move |by| (bx, by)))
+                .filter_map(|bucket| self.buckets.get(&bucket))
+                .sum()
+        }
+    }
+
+    /// Whether a hit at the cell counts towards the area's neighbours.
+    pub fn touches(&self, area: &Area, x: u64, y: u64) -> bool {
+        let (min_x, min_y, max_x, max_y) = reach(area);
+        let (bx, by) = (x / CLUSTER_RADIUS, y / CLUSTER_RADIUS);
+        self.enabled && (min_x..=max_x).contains(&bx) && (min_y..=max_y).contains(&by)
+    }
+}
+
+// buckets within `CLUSTER_RADIUS` of the area, bounds included
+fn reach(area: &Area) -> (u64, u64, u64, u64) {
+    (
+        area.pos_x.saturating_sub(CLUSTER_RADIUS) / CLUSTER_RADIUS,
+        area.pos_y.saturating_sub(CLUSTER_RADIUS) / CLUSTER_RADIUS,
+        (area.pos_x + area.size_x + CLUSTER_RADIUS) / CLUSTER_RADIUS,
+        (area.pos_y + area.size_y + CLUSTER_RADIUS) / CLUSTER_RADIUS,
+    )
+}
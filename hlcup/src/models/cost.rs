@@ -0,0 +1,94 @@
+// a bucket needs this many explores before its mean is trusted
+const MIN_SAMPLES: u64 = 10;
+// explores up to this many times slower than the smallest ones still count as cheap
+const KNEE_FACTOR: f64 = 2.;
+
+/// Log2 bucket of an area size: 1 -> 1, 2..3 -> 2, 4..7 -> 3, ...
+pub fn size_bucket(size: u64) -> u8 {
+    (64 - size.leading_zeros()) as u8
+}
+
+fn bucket_max_size(bucket: u8) -> u64 {
+    if bucket >= 64 {
+        u64::MAX
+    } else {
+        (1 << bucket) - 1
+    }
+}
+
+/// Explore latency as a function of area size, fitted on mean latencies
+/// of the size buckets recorded by `Stats`.
+#[derive(Debug, Default, Clone)]
+pub struct ExploreCostModel {
+    // (bucket, mean latency in us), sorted by bucket
+    points: Vec<(u8, f64)>,
+    // least squares fit of latency over bucket: intercept, slope
+    fit: Option<(f64, f64)>,
+}
+
+impl ExploreCostModel {
+    pub fn new(samples: impl IntoIterator<Item = (u8, f64, u64)>) -> Self {
+        let mut points = samples
+            .into_iter()
+            .filter(|(_, _, count)| *count >= MIN_SAMPLES)
+            .map(|(bucket, mean, _)| (bucket, mean))
+            .collect::<Vec<(u8, f64)>>();
+        points.sort_by_key(|(bucket, _)| *bucket);
+
+        let fit = if points.len() >= 2 {
+            let n = points.len() as f64;
+            let mean_x = points.iter().map(|(b, _)| *b as f64).sum::<f64>() / n;
+            let mean_y = points.iter().map(|(_, c)| c).sum::<f64>() / n;
+            let cov = points
+                .iter()
+                .map(|(b, c)| (*b as f64 - mean_x) * (c - mean_y))
+                .sum::<f64>();
+            let var = points
+                .iter()
+                .map(|(b, _)| (*b as f64 - mean_x).powi(2))
+                .sum::<f64>();
+            let slope = cov / var;
+            Some((mean_y - slope * mean_x, slope))
+        } else {
+            None
+        };
+
+        Self { points, fit }
+    }
+
+    /// Expected latency of a single explore of an area of `size`,
+    /// interpolated between measured buckets and extrapolated with the fit outside of them.
+    pub fn cost_us(&self, size: u64) -> Option<f64> {
+        let bucket = size_bucket(size);
+        let upper = self.points.iter().position(|(b, _)| *b >= bucket);
+        match upper {
+            Some(i) if self.points[i].0 == bucket => Some(self.points[i].1),
+            Some(i) if i > 0 => {
+                let (b0, c0) = self.points[i - 1];
+                let (b1, c1) = self.points[i];
+                let t = (bucket - b0) as f64 / (b1 - b0) as f64;
+                Some(c0 + (c1 - c0) * t)
+            }
+            _ => match self.fit {
+                Some((intercept, slope)) => Some((intercept + slope * bucket as f64).max(0.)),
+                None => self.points.first().map(|(_, c)| *c),
+            },
+        }
+    }
+
+    /// Largest area size that still explores about as fast as the smallest measured one,
+    /// `None` while the curve has not been seen bending.
+    pub fn knee_size(&self) -> Option<u64> {
+        let (_, base) = self.points.first()?;
+        let flat = self
+            .points
+            .iter()
+            .take_while(|(_, cost)| *cost <= base * KNEE_FACTOR)
+            .count();
+        if flat < self.points.len() {
+            Some(bucket_max_size(self.points[flat - 1].0))
+        } else {
+            None
+        }
+    }
+}
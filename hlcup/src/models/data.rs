@@ -1,34 +1,39 @@
 use crate::http::dto::Dig;
-use std::cmp::Ordering;
+use crate::models::priority::Score;
+use crate::models::trace::TraceId;
 
 #[derive(Debug)]
 pub struct Treasures {
     pub depth: u8,
     pub treasures: Vec<String>,
+    pub trace: TraceId,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Treasure {
     pub depth: u8,
     pub treasure: String,
+    pub trace: TraceId,
 }
 
 impl Treasure {
-    pub fn new(depth: u8, treasure: String) -> Self {
-        Self { depth, treasure }
+    pub fn new(depth: u8, treasure: String, trace: TraceId) -> Self {
+        Self {
+            depth,
+            treasure,
+            trace,
+        }
     }
-}
 
-impl Ord for Treasure {
-    fn cmp(&self, other: &Self) -> Ordering {
+    /// Deeper treasures are cashed first, equal depths are ordered by treasure id.
+    pub fn priority(&self) -> Score {
         // todo: other kind of priority
-        self.depth.cmp(&other.depth)
-    }
-}
-
-impl PartialOrd for Treasure {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+        let tie = self
+            .treasure
+            .bytes()
+            .take(16)
+            .fold(0u128, |acc, b| acc << 8 | b as u128);
+        Score::ratio(self.depth as u128, 1).then(tie)
     }
 }
 
@@ -38,15 +43,17 @@ pub struct PendingDig {
     pub y: u64,
     pub depth: u8,
     pub remaining: u64,
+    pub trace: TraceId,
 }
 
 impl PendingDig {
-    pub fn new(x: u64, y: u64, remaining: u64) -> PendingDig {
+    pub fn new(x: u64, y: u64, remaining: u64, trace: TraceId) -> PendingDig {
         PendingDig {
             x,
             y,
             depth: 1,
             remaining,
+            trace,
         }
     }
 
@@ -71,15 +78,3 @@ impl PendingDig {
         }
     }
 }
-
-impl Ord for PendingDig {
-    fn cmp(&self, other: &Self) -> Ordering {
-        (self.remaining * self.depth as u64).cmp(&(other.remaining * other.depth as u64))
-    }
-}
-
-impl PartialOrd for PendingDig {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
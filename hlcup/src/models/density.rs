@@ -0,0 +1,62 @@
+use crate::http::dto::Explore;
+
+// Gamma prior on treasures per cell, worth this many cells of observations
+const PRIOR_TREASURES: f64 = 1.;
+const PRIOR_CELLS: f64 = 25.;
+
+/// Posterior estimate of treasure density built from explores of areas nothing was known about.
+/// Parts of an area known to hold treasure are explored only while treasure is left in it
+/// and narrowed down only where some is found, they would inflate the rate.
+#[derive(Debug, Clone)]
+pub struct DensityModel {
+    treasures: f64,
+    cells: f64,
+}
+
+impl DensityModel {
+    pub fn new() -> Self {
+        Self {
+            treasures: PRIOR_TREASURES,
+            cells: PRIOR_CELLS,
+        }
+    }
+
+    /// `within` is the amount of the area the explored one is a part of, `u64::MAX` when unknown.
+    pub fn observe(&mut self, explore: &Explore, within: u64) {
+        if within == u64::MAX && explore.amount != u64::MAX {
+            self.treasures += explore.amount as f64;
+            self.cells += explore.area.size() as f64;
+        }
+    }
+
+    /// Posterior mean of treasures per cell.
+    pub fn rate(&self) -> f64 {
+        self.treasures / self.cells
+    }
+
+    /// Treasures in the area, the posterior mean when its amount is not known.
+    pub fn expected_amount(&self, explore: &Explore) -> f64 {
+        if explore.amount == u64::MAX {
+            self.rate() * explore.area.size() as f64
+        } else {
+            explore.amount as f64
+        }
+    }
+
+    /// Treasures expected in a single cell of the area, rounded up so each cell is worth one dig.
+    pub fn cell_treasures(&self, explore: &Explore) -> u64 {
+        let per_cell = self.expected_amount(explore) / explore.area.size() as f64;
+        (per_cell.ceil() as u64).max(1)
+    }
+
+    /// Probability that a given cell of the area holds at least one treasure.
+    pub fn cell_probability(&self, explore: &Explore) -> f64 {
+        if explore.amount == u64::MAX {
+            1. - (-self.rate()).exp()
+        } else {
+            // the amount is known exactly, treasures are placed uniformly within the area
+            let size = explore.area.size() as f64;
+            1. - (1. - 1. / size).powf(explore.amount as f64)
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+// weight of the prior, in digs
+const PRIOR_DIGS: f64 = 10.;
+// coins per treasure assumed at depths nothing was cashed from yet
+const PRIOR_COINS: f64 = 1.;
+
+#[derive(Debug, Default, Clone)]
+struct DepthSample {
+    digs: f64,
+    found: f64,
+    coins: Option<f64>,
+}
+
+/// Expected coins of digging one more level of a cell, estimated per depth
+/// from found rates and cashed coins recorded by `Stats`.
+#[derive(Debug, Default, Clone)]
+pub struct DepthModel {
+    depths: BTreeMap<u8, DepthSample>,
+}
+
+impl DepthModel {
+    pub fn new(
+        found_per_depth: &BTreeMap<u8, (f64, f64)>,
+        coins_per_depth: impl IntoIterator<Item = (u8, f64)>,
+    ) -> Self {
+        let mut depths = found_per_depth
+            .iter()
+            .map(|(depth, (digs, found))| {
+                let sample = DepthSample {
+                    digs: *digs,
+                    found: *found,
+                    coins: None,
+                };
+                (*depth, sample)
+            })
+            .collect::<BTreeMap<u8, DepthSample>>();
+        for (depth, coins) in coins_per_depth {
+            depths.entry(depth).or_default().coins = Some(coins);
+        }
+        Self { depths }
+    }
+
+    /// Chance that a treasure not found above `depth` lies at `depth`,
+    /// starting from treasures being spread evenly over the depths left.
+    pub fn hazard(&self, depth: u8, max_depth: u8) -> f64 {
+        if depth > max_depth {
+            return 0.;
+        }
+        let prior = 1. / (max_depth + 1 - depth) as f64;
+        let (digs, found) = self
+            .depths
+            .get(&depth)
+            .map(|s| (s.digs, s.found))
+            .unwrap_or((0., 0.));
+        ((found + PRIOR_DIGS * prior) / (digs + PRIOR_DIGS)).min(1.)
+    }
+
+    pub fn coins(&self, depth: u8) -> f64 {
+        self.depths
+            .get(&depth)
+            .and_then(|s| s.coins)
+            .unwrap_or(PRIOR_COINS)
+    }
+
+    /// Coins expected from digging `depth` of a cell still holding `remaining` treasures,
+    /// each of them lying at this depth with the chance given by the hazard.
+    pub fn expected_coins(&self, depth: u8, remaining: u64, max_depth: u8) -> f64 {
+        let remaining = remaining.min(i32::MAX as u64) as i32;
+        let none_here = (1. - self.hazard(depth, max_depth)).powi(remaining);
+        (1. - none_here) * self.coins(depth)
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::constants::MAX_MAP_AREAS;
+use crate::http::dto::Area;
+use crate::models::ledger::{DigOutcome, DigRecord};
+use crate::models::metrics::Origin;
+
+// explored areas without treasure stay visible next to unexplored ground
+const EXPLORED_EMPTY: u8 = 40;
+
+/// Grayscale image with one pixel per cell.
+pub struct Layer {
+    width: u64,
+    height: u64,
+    pixels: Vec<u8>,
+}
+
+impl Layer {
+    fn new(width: u64, height: u64) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height) as usize],
+        }
+    }
+
+    fn set(&mut self, x: u64, y: u64, value: u8) {
+        if x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = value;
+        }
+    }
+
+    fn fill(&mut self, area: &Area, value: u8) {
+        let x_end = (area.pos_x + area.size_x).min(self.width);
+        for y in area.pos_y..(area.pos_y + area.size_y).min(self.height) {
+            let row = (y * self.width) as usize;
+            let (from, to) = (row + area.pos_x.min(x_end) as usize, row + x_end as usize);
+            self.pixels[from..to].iter_mut().for_each(|p| *p = value);
+        }
+    }
+
+    /// Binary PGM, opened by most image viewers.
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        out.extend_from_slice(&self.pixels);
+        out
+    }
+}
+
+// brightest for the largest value
+fn shade(value: f64, max: f64) -> u8 {
+    if max > 0. {
+        (EXPLORED_EMPTY as f64 + (255 - EXPLORED_EMPTY) as f64 * value / max).round() as u8
+    } else {
+        EXPLORED_EMPTY
+    }
+}
+
+/// Explore results and per-cell dig histories of a run.
+#[derive(Default)]
+pub struct FieldMap {
+    pub areas: Vec<(Area, u64)>,
+    pub cells: HashMap<(u64, u64), (Origin, Vec<DigRecord>)>,
+}
+
+impl FieldMap {
+    /// Nothing is kept past `MAX_MAP_AREAS` areas.
+    pub fn record_area(&mut self, area: Area, amount: u64) {
+        if self.areas.len() < MAX_MAP_AREAS {
+            self.areas.push((area, amount));
+        }
+    }
+
+    /// Smallest rectangle from the origin holding everything recorded.
+    pub fn bounds(&self) -> (u64, u64) {
+        let areas = self
+            .areas
+            .iter()
+            .map(|(a, _)| (a.pos_x + a.size_x, a.pos_y + a.size_y));
+        let cells = self.cells.keys().map(|(x, y)| (x + 1, y + 1));
+        areas
+            .chain(cells)
+            .fold((0, 0), |(w, h), (x, y)| (w.max(x), h.max(y)))
+    }
+
+    /// Treasures per cell of every explored area, smaller areas drawn over the ones they came from.
+    pub fn explored(&self, width: u64, height: u64) -> Layer {
+        let density = |(area, amount): &(Area, u64)| *amount as f64 / area.size() as f64;
+        let max = self.areas.iter().map(density).fold(0., f64::max);
+        let mut areas = self.areas.iter().collect::<Vec<_>>();
+        areas.sort_by_key(|(area, _)| std::cmp::Reverse(area.size()));
+
+        let mut layer = Layer::new(width, height);
+        for explored in areas {
+            layer.fill(&explored.0, shade(density(explored), max));
+        }
+        layer
+    }
+
+    /// Deepest level answered per cell.
+    pub fn dug(&self, width: u64, height: u64) -> Layer {
+        let depths = self
+            .cells
+            .iter()
+            .map(|(cell, (_, history))| (*cell, deepest(history)))
+            .collect::<Vec<_>>();
+        let max = depths.iter().map(|(_, depth)| *depth).max().unwrap_or(0);
+        let mut layer = Layer::new(width, height);
+        for ((x, y), depth) in depths {
+            layer.set(x, y, shade(depth as f64, max as f64));
+        }
+        layer
+    }
+
+    /// Treasures brought up per cell, cells without any stay dark.
+    pub fn found(&self, width: u64, height: u64) -> Layer {
+        let found = self
+            .cells
+            .iter()
+            .map(|(cell, (_, history))| (*cell, found_in(history)))
+            .filter(|(_, found)| *found > 0)
+            .collect::<Vec<_>>();
+        let max = found.iter().map(|(_, found)| *found).max().unwrap_or(0);
+        let mut layer = Layer::new(width, height);
+        for ((x, y), count) in found {
+            layer.set(x, y, shade(count as f64, max as f64));
+        }
+        layer
+    }
+
+    /// Writes `explored.pgm`, `dug.pgm`, `found.pgm`, `areas.csv` and `cells.csv` into `dir`.
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let (width, height) = self.bounds();
+        std::fs::write(dir.join("explored.pgm"), self.explored(width, height).to_pgm())?;
+        std::fs::write(dir.join("dug.pgm"), self.dug(width, height).to_pgm())?;
+        std::fs::write(dir.join("found.pgm"), self.found(width, height).to_pgm())?;
+        std::fs::write(dir.join("areas.csv"), self.areas_csv())?;
+        std::fs::write(dir.join("cells.csv"), self.cells_csv())
+    }
+
+    pub fn areas_csv(&self) -> String {
+        let mut out = String::from("pos_x,pos_y,size_x,size_y,amount\n");
+        for (area, amount) in self.areas.iter() {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                area.pos_x, area.pos_y, area.size_x, area.size_y, amount
+            );
+        }
+        out
+    }
+
+    /// One row per dig, failed ones and ones with an unknown outcome have no treasure count.
+    pub fn cells_csv(&self) -> String {
+        let mut cells = self.cells.iter().collect::<Vec<_>>();
+        cells.sort_by_key(|(cell, _)| **cell);
+        let mut out = String::from("x,y,origin,depth,license,found\n");
+        for ((x, y), (origin, history)) in cells {
+            for record in history.iter() {
+                let found = match record.outcome {
+                    DigOutcome::Found(found) => found.to_string(),
+                    DigOutcome::Failed | DigOutcome::WrongDepth | DigOutcome::Unknown => {
+                        String::new()
+                    }
+                };
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    x, y, origin, record.depth, record.license, found
+                );
+            }
+        }
+        out
+    }
+}
+
+fn found_in(history: &[DigRecord]) -> u64 {
+    history
+        .iter()
+        .map(|r| match r.outcome {
+            DigOutcome::Found(found) => found,
+            DigOutcome::Failed | DigOutcome::WrongDepth | DigOutcome::Unknown => 0,
+        })
+        .sum()
+}
+
+fn deepest(history: &[DigRecord]) -> u8 {
+    history
+        .iter()
+        .filter(|r| matches!(r.outcome, DigOutcome::Found(_)))
+        .map(|r| r.depth)
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::models::data::PendingDig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigOutcome {
+    Found(u64),
+    /// rejected by the server, the level was not dug
+    Failed,
+    /// rejected for not being the level right below the deepest one dug
+    WrongDepth,
+    /// timed out or failed on the server side, the level may have been dug
+    Unknown,
+}
+
+/// Single dig request made at a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigRecord {
+    pub depth: u8,
+    pub license: u64,
+    pub outcome: DigOutcome,
+}
+
+#[derive(Debug, Default)]
+struct CellDigs {
+    history: Vec<DigRecord>,
+}
+
+impl CellDigs {
+    // the server only accepts the level right below the deepest one dug so far
+    fn next_depth(&self) -> u8 {
+        let mut deepest = 0;
+        let mut ambiguous = None;
+        for record in self.history.iter() {
+            match record.outcome {
+                DigOutcome::Found(_) => deepest = deepest.max(record.depth),
+                DigOutcome::Unknown => ambiguous = Some(record.depth),
+                // told the level is dug right after an attempt that may have gone through: it did
+                DigOutcome::WrongDepth if ambiguous == Some(record.depth) => {
+                    deepest = deepest.max(record.depth)
+                }
+                DigOutcome::Failed | DigOutcome::WrongDepth => (),
+            }
+        }
+        deepest + 1
+    }
+}
+
+/// Depths dug at every cell still being dug, with the license used and what came out.
+/// Every cell has a single dig in flight, so cells are forgotten once they are done with.
+#[derive(Debug, Default)]
+pub struct DigLedger {
+    cells: HashMap<(u64, u64), CellDigs>,
+}
+
+impl DigLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_depth(&self, x: u64, y: u64) -> u8 {
+        self.cells.get(&(x, y)).map_or(1, CellDigs::next_depth)
+    }
+
+    /// Moves the dig to the level the cell is at,
+    /// `false` once every level down to `max_depth` was dug.
+    pub fn align(&self, dig: &mut PendingDig, max_depth: u8) -> bool {
+        dig.depth = self.next_depth(dig.x, dig.y);
+        dig.depth <= max_depth
+    }
+
+    pub fn record(&mut self, x: u64, y: u64, record: DigRecord) {
+        self.cells.entry((x, y)).or_default().history.push(record);
+    }
+
+    /// Forgets the cell and returns its history.
+    pub fn finish(&mut self, x: u64, y: u64) -> Vec<DigRecord> {
+        self.cells.remove(&(x, y)).map(|cell| cell.history).unwrap_or_default()
+    }
+}
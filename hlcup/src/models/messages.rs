@@ -1,8 +1,9 @@
-use reqwest::StatusCode;
 use tokio::sync::oneshot;
 
-use crate::http::dto::License;
+use crate::http::dto::{Area, License};
 use crate::models::data::Treasures;
+use crate::models::ledger::DigRecord;
+use crate::models::metrics::Origin;
 
 #[derive(Debug)]
 pub enum MessageForAccounting {
@@ -11,32 +12,27 @@ pub enum MessageForAccounting {
     LicenseExpired(u64),
 }
 
+/// Requests themselves are counted in `metrics::Registry`, per origin as well.
 #[derive(Debug)]
 pub enum StatsMessage {
     ShowStats,
-    RecordExplore {
-        area_size: u64,
-        duration: u64,
-        status: Option<StatusCode>,
-    },
-    RecordDig {
-        depth: u8,
+    /// Shown stats and reports start over from here.
+    ResetStats,
+    RecordCell {
+        origin: Origin,
         x: u64,
         y: u64,
-        duration: u64,
-        found: bool,
-        status: Option<StatusCode>,
+        history: Vec<DigRecord>,
     },
-    RecordCash {
-        depth: u8,
+    /// Amount found by an explore, kept for the field map.
+    RecordArea {
+        area: Area,
         amount: u64,
-        duration: u64,
-        status: Option<StatusCode>,
-    },
-    RecordLicense {
-        duration: u64,
-        coins: u64,
-        allowed: u8,
-        status: Option<StatusCode>,
     },
+    /// Closes the current time series window.
+    Sample,
+    /// Writes the report and the time series to the configured files, answers once they are on disk.
+    WriteReport(oneshot::Sender<()>),
+    /// Writes field map images and CSVs, only done once the game is over.
+    WriteMap(oneshot::Sender<()>),
 }
@@ -0,0 +1,851 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+
+use crate::http::breaker::BreakerState;
+use crate::http::scheduler::Endpoint;
+use crate::models::cost::size_bucket;
+
+// every power of two is split in this many buckets, about 12% precision
+const SUB_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+const BUCKETS: usize = (64 - SUB_BITS as usize + 1) * SUB_BUCKETS;
+// histogram keys are size buckets, depths or endpoints, all of them below this
+const MAX_KEYS: usize = 65;
+const MAX_STATUS: usize = 600;
+const MAX_COINS: usize = 64;
+// distinct error messages kept per endpoint and shard, and how much of each
+const MAX_ERROR_SAMPLES: usize = 8;
+const MAX_SAMPLE_LEN: usize = 200;
+
+fn counters(n: usize) -> Vec<AtomicU64> {
+    (0..n).map(|_| AtomicU64::new(0)).collect()
+}
+
+fn bucket(value: u64) -> usize {
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+    let exp = 63 - value.leading_zeros();
+    let sub = (value >> (exp - SUB_BITS)) as usize & (SUB_BUCKETS - 1);
+    (exp - SUB_BITS + 1) as usize * SUB_BUCKETS + sub
+}
+
+// smallest and largest values falling into the bucket
+fn bucket_bounds(index: usize) -> (u64, u64) {
+    if index < SUB_BUCKETS {
+        return (index as u64, index as u64);
+    }
+    let exp = (index / SUB_BUCKETS) as u32 + SUB_BITS - 1;
+    let sub = (index % SUB_BUCKETS) as u64;
+    let width = 1u64 << (exp - SUB_BITS);
+    let low = (SUB_BUCKETS as u64 + sub) << (exp - SUB_BITS);
+    (low, low + (width - 1))
+}
+
+/// Log-linear histogram recorded with relaxed atomic adds only.
+struct AtomicHistogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl AtomicHistogram {
+    fn new() -> Self {
+        Self {
+            buckets: counters(BUCKETS),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, value: u64) {
+        self.buckets[bucket(value)].fetch_add(1, Relaxed);
+        self.count.fetch_add(1, Relaxed);
+        self.sum.fetch_add(value, Relaxed);
+        self.min.fetch_min(value, Relaxed);
+        self.max.fetch_max(value, Relaxed);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count.load(Relaxed) == 0
+    }
+}
+
+/// Plain copy of one or more merged histograms.
+#[derive(Debug, Clone)]
+pub struct HistogramSnapshot {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Default for HistogramSnapshot {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            sum: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+}
+
+impl HistogramSnapshot {
+    fn add(&mut self, other: &AtomicHistogram) {
+        for (b, o) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *b += o.load(Relaxed);
+        }
+        self.count += other.count.load(Relaxed);
+        self.sum = self.sum.saturating_add(other.sum.load(Relaxed));
+        self.min = self.min.min(other.min.load(Relaxed));
+        self.max = self.max.max(other.max.load(Relaxed));
+    }
+
+    fn merge(&mut self, other: &HistogramSnapshot) {
+        for (b, o) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *b += o;
+        }
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Values recorded after `earlier` was taken, minimum and maximum are bucket bounds.
+    pub fn since(&self, earlier: &HistogramSnapshot) -> HistogramSnapshot {
+        let buckets: Vec<u64> = self
+            .buckets
+            .iter()
+            .zip(earlier.buckets.iter())
+            .map(|(b, e)| b.saturating_sub(*e))
+            .collect();
+        let filled = || buckets.iter().enumerate().filter(|(_, count)| **count > 0);
+        let min = filled().next().map_or(u64::MAX, |(index, _)| bucket_bounds(index).0);
+        let max = filled().next_back().map_or(0, |(index, _)| bucket_bounds(index).1);
+        HistogramSnapshot {
+            count: self.count.saturating_sub(earlier.count),
+            sum: self.sum.saturating_sub(earlier.sum),
+            buckets,
+            min,
+            max,
+        }
+    }
+
+    pub fn entries(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// Number of values up to each of the `bounds`. Values are counted by bucket,
+    /// a bucket reaching past a bound is left to the next one.
+    pub fn cumulative(&self, bounds: &[u64]) -> Vec<(u64, u64)> {
+        bounds
+            .iter()
+            .map(|bound| {
+                let count = self
+                    .buckets
+                    .iter()
+                    .enumerate()
+                    .take_while(|(index, _)| bucket_bounds(*index).1 <= *bound)
+                    .map(|(_, count)| count)
+                    .sum();
+                (*bound, count)
+            })
+            .collect()
+    }
+
+    pub fn minimum(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn maximum(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Upper bound of the bucket holding the percentile, never above the maximum.
+    pub fn percentile(&self, percentile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100. * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(bucket_bounds(index).1.min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Estimated from bucket midpoints.
+    pub fn stddev(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let variance = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(index, count)| {
+                let (low, high) = bucket_bounds(index);
+                let mid = (low as f64 + high as f64) / 2.;
+                *count as f64 * (mid - mean).powi(2)
+            })
+            .sum::<f64>()
+            / self.count as f64;
+        Some(variance.sqrt())
+    }
+}
+
+/// Latencies per key, error statuses and a few error messages of one endpoint.
+struct EndpointMetrics {
+    histograms: Vec<AtomicHistogram>,
+    statuses: Vec<AtomicU64>,
+    // only touched on errors
+    samples: Mutex<Vec<(String, u64)>>,
+}
+
+impl EndpointMetrics {
+    fn new() -> Self {
+        Self {
+            histograms: (0..MAX_KEYS).map(|_| AtomicHistogram::new()).collect(),
+            statuses: counters(MAX_STATUS),
+            samples: Mutex::new(Vec::with_capacity(MAX_ERROR_SAMPLES)),
+        }
+    }
+
+    /// Never waits, a sample is dropped while a snapshot holds the lock.
+    fn sample(&self, message: &str) {
+        let mut samples = match self.samples.try_lock() {
+            Ok(samples) => samples,
+            Err(_) => return,
+        };
+        let message = match message.char_indices().nth(MAX_SAMPLE_LEN) {
+            Some((end, _)) => &message[..end],
+            None => message,
+        };
+        if let Some((_, count)) = samples.iter_mut().find(|(m, _)| m == message) {
+            *count += 1;
+        } else if samples.len() < MAX_ERROR_SAMPLES {
+            samples.push((message.to_string(), 1));
+        }
+    }
+
+    fn record(&self, key: u8, value: u64, err: Option<StatusCode>) {
+        self.histograms[(key as usize).min(MAX_KEYS - 1)].record(value);
+        if let Some(status) = err {
+            self.statuses[(status.as_u16() as usize).min(MAX_STATUS - 1)].fetch_add(1, Relaxed);
+        }
+    }
+}
+
+// counts that grew since `earlier`, the ones that did not are left out
+fn counts_since<K: Ord + Clone>(now: &BTreeMap<K, u64>, earlier: &BTreeMap<K, u64>) -> BTreeMap<K, u64> {
+    now.iter()
+        .map(|(key, count)| (key.clone(), count.saturating_sub(*earlier.get(key).unwrap_or(&0))))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// Merged view of an endpoint across every shard.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+    pub histograms: BTreeMap<u8, HistogramSnapshot>,
+    // error responses per status code
+    pub statuses: BTreeMap<u16, u64>,
+    /// distinct error messages with how often each was seen
+    pub samples: BTreeMap<String, u64>,
+}
+
+impl EndpointStats {
+    fn add(&mut self, metrics: &EndpointMetrics) {
+        for (key, h) in metrics.histograms.iter().enumerate() {
+            if !h.is_empty() {
+                self.histograms.entry(key as u8).or_default().add(h);
+            }
+        }
+        for (status, count) in metrics.statuses.iter().enumerate() {
+            let count = count.load(Relaxed);
+            if count > 0 {
+                *self.statuses.entry(status as u16).or_insert(0) += count;
+            }
+        }
+        let samples = metrics.samples.lock().expect("error samples lock poisoned");
+        for (message, count) in samples.iter() {
+            *self.samples.entry(message.clone()).or_insert(0) += count;
+        }
+    }
+
+    /// What was recorded after `earlier` was taken.
+    pub fn since(&self, earlier: &EndpointStats) -> EndpointStats {
+        let histograms = self
+            .histograms
+            .iter()
+            .filter_map(|(key, h)| {
+                let h = match earlier.histograms.get(key) {
+                    Some(e) => h.since(e),
+                    None => h.clone(),
+                };
+                if h.entries() > 0 { Some((*key, h)) } else { None }
+            })
+            .collect();
+        EndpointStats {
+            histograms,
+            statuses: counts_since(&self.statuses, &earlier.statuses),
+            samples: counts_since(&self.samples, &earlier.samples),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.histograms.values().map(HistogramSnapshot::entries).sum()
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.statuses.values().sum()
+    }
+
+    /// Every key folded into a single histogram.
+    pub fn merged(&self) -> HistogramSnapshot {
+        let mut merged = HistogramSnapshot::default();
+        for h in self.histograms.values() {
+            merged.merge(h);
+        }
+        merged
+    }
+
+    /// Mean and number of samples per histogram key.
+    pub fn means(&self) -> Vec<(u8, f64, u64)> {
+        self.histograms
+            .iter()
+            .filter_map(|(key, h)| h.mean().map(|mean| (*key, mean, h.entries())))
+            .collect()
+    }
+
+    pub fn percentiles(&self, percentile: f64) -> BTreeMap<u8, u64> {
+        self.histograms
+            .iter()
+            .filter_map(|(key, h)| h.percentile(percentile).map(|p| (*key, p)))
+            .collect()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let (sum, count) = self
+            .means()
+            .into_iter()
+            .fold((0., 0.), |(sum, count), (_, mean, n)| (sum + mean * n as f64, count + n as f64));
+        if count > 0. {
+            Some(sum / count)
+        } else {
+            None
+        }
+    }
+}
+
+/// Actor a shard or a stats message comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    Accounting,
+    Worker(u64),
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Accounting => write!(f, "accounting"),
+            Origin::Worker(index) => write!(f, "worker-{}", index),
+        }
+    }
+}
+
+/// What a worker spends its wall-clock time on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Activity {
+    Explore,
+    Dig,
+    LicenseWait,
+    // sending to accounting
+    Mailbox,
+    // snapshotting the registry for the planner
+    Stats,
+    // backing off from an open circuit
+    Idle,
+}
+
+impl Activity {
+    pub const ALL: [Activity; 6] = [
+        Activity::Explore,
+        Activity::Dig,
+        Activity::LicenseWait,
+        Activity::Mailbox,
+        Activity::Stats,
+        Activity::Idle,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activity::Explore => "explore",
+            Activity::Dig => "dig",
+            Activity::LicenseWait => "license_wait",
+            Activity::Mailbox => "mailbox",
+            Activity::Stats => "stats",
+            Activity::Idle => "idle",
+        }
+    }
+}
+
+/// Time a worker spent per activity since its shard was handed out,
+/// whatever is not attributed is planning and bookkeeping.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkerTimes {
+    pub wall_us: u64,
+    pub spent_us: BTreeMap<Activity, u64>,
+}
+
+impl WorkerTimes {
+    pub fn other_us(&self) -> u64 {
+        self.wall_us.saturating_sub(self.spent_us.values().sum())
+    }
+
+    pub fn since(&self, earlier: &WorkerTimes) -> WorkerTimes {
+        WorkerTimes {
+            wall_us: self.wall_us.saturating_sub(earlier.wall_us),
+            spent_us: counts_since(&self.spent_us, &earlier.spent_us),
+        }
+    }
+
+    pub fn share(&self, us: u64) -> f64 {
+        if self.wall_us > 0 {
+            us as f64 / self.wall_us as f64
+        } else {
+            0.
+        }
+    }
+}
+
+/// Totals of a single shard, next to the merged ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OriginStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub digs: u64,
+    pub found: u64,
+    pub times: WorkerTimes,
+}
+
+impl OriginStats {
+    pub fn since(&self, earlier: &OriginStats) -> OriginStats {
+        OriginStats {
+            requests: self.requests.saturating_sub(earlier.requests),
+            errors: self.errors.saturating_sub(earlier.errors),
+            timeouts: self.timeouts.saturating_sub(earlier.timeouts),
+            digs: self.digs.saturating_sub(earlier.digs),
+            found: self.found.saturating_sub(earlier.found),
+            times: self.times.since(&earlier.times),
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.requests > 0 {
+            self.errors as f64 / self.requests as f64
+        } else {
+            0.
+        }
+    }
+
+    pub fn found_rate(&self) -> f64 {
+        if self.digs > 0 {
+            self.found as f64 / self.digs as f64
+        } else {
+            0.
+        }
+    }
+}
+
+/// Latest values shared by every shard.
+struct Gauges {
+    limits: Vec<AtomicU64>,
+    coins: AtomicU64,
+    active_licenses: AtomicU64,
+    treasures: AtomicU64,
+}
+
+impl Gauges {
+    fn new() -> Self {
+        Self {
+            limits: counters(Endpoint::ALL.len()),
+            coins: AtomicU64::new(0),
+            active_licenses: AtomicU64::new(0),
+            treasures: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Counters written by a single worker. Recording never blocks nor allocates,
+/// `Registry` merges every shard when stats are asked for.
+pub struct Metrics {
+    explore: EndpointMetrics,
+    dig: EndpointMetrics,
+    cash: EndpointMetrics,
+    license: EndpointMetrics,
+    cash_at_depth: EndpointMetrics,
+    queue: EndpointMetrics,
+    digs_per_depth: Vec<AtomicU64>,
+    found_per_depth: Vec<AtomicU64>,
+    licenses_per_coins: Vec<AtomicU64>,
+    digs_allowed: AtomicU64,
+    timeouts: Vec<AtomicU64>,
+    hedges_won: AtomicU64,
+    hedges_lost: AtomicU64,
+    // transitions into each breaker state per endpoint
+    breaker: Vec<AtomicU64>,
+    gauges: Arc<Gauges>,
+    origin: Origin,
+    created: Instant,
+    times: Vec<AtomicU64>,
+}
+
+impl Metrics {
+    fn new(gauges: Arc<Gauges>, origin: Origin) -> Self {
+        Self {
+            origin,
+            created: Instant::now(),
+            times: counters(Activity::ALL.len()),
+            explore: EndpointMetrics::new(),
+            dig: EndpointMetrics::new(),
+            cash: EndpointMetrics::new(),
+            license: EndpointMetrics::new(),
+            cash_at_depth: EndpointMetrics::new(),
+            queue: EndpointMetrics::new(),
+            digs_per_depth: counters(MAX_KEYS),
+            found_per_depth: counters(MAX_KEYS),
+            licenses_per_coins: counters(MAX_COINS),
+            digs_allowed: AtomicU64::new(0),
+            timeouts: counters(Endpoint::ALL.len()),
+            hedges_won: AtomicU64::new(0),
+            hedges_lost: AtomicU64::new(0),
+            breaker: counters(Endpoint::ALL.len() * 3),
+            gauges,
+        }
+    }
+
+    fn endpoint(&self, endpoint: Endpoint) -> &EndpointMetrics {
+        match endpoint {
+            Endpoint::Cash => &self.cash,
+            Endpoint::License => &self.license,
+            Endpoint::Dig => &self.dig,
+            Endpoint::Explore => &self.explore,
+        }
+    }
+
+    pub fn record_explore(&self, area_size: u64, duration: u64, err: Option<StatusCode>) {
+        self.explore.record(size_bucket(area_size), duration, err);
+    }
+
+    pub fn record_dig(&self, depth: u8, duration: u64, found: bool, err: Option<StatusCode>) {
+        self.dig.record(depth, duration, err);
+        let depth = (depth as usize).min(MAX_KEYS - 1);
+        self.digs_per_depth[depth].fetch_add(1, Relaxed);
+        if found {
+            self.found_per_depth[depth].fetch_add(1, Relaxed);
+        }
+    }
+
+    pub fn record_cash(&self, depth: u8, amount: u64, duration: u64, err: Option<StatusCode>) {
+        self.cash.record(depth, duration, err);
+        // failed cashes would drag the mean coins per treasure down
+        if err.is_none() {
+            self.cash_at_depth.record(depth, amount, None);
+        }
+    }
+
+    pub fn record_license(&self, duration: u64, coins: u64, allowed: u8, err: Option<StatusCode>) {
+        self.license.record(0, duration, err);
+        if err.is_none() {
+            self.licenses_per_coins[(coins as usize).min(MAX_COINS - 1)].fetch_add(1, Relaxed);
+            self.digs_allowed.fetch_add(allowed as u64, Relaxed);
+        }
+    }
+
+    pub fn record_queue(&self, endpoint: Endpoint, delay: u64) {
+        self.queue.record(endpoint.index() as u8, delay, None);
+    }
+
+    pub fn record_limit(&self, endpoint: Endpoint, limit: usize) {
+        self.gauges.limits[endpoint.index()].store(limit as u64, Relaxed);
+    }
+
+    /// Coins held, licenses not yet used up and treasures waiting to be cashed by accounting.
+    pub fn record_balance(&self, coins: u64, active_licenses: u8, treasures: u64) {
+        self.gauges.coins.store(coins, Relaxed);
+        self.gauges.active_licenses.store(active_licenses as u64, Relaxed);
+        self.gauges.treasures.store(treasures, Relaxed);
+    }
+
+    /// Keeps the message when it is one of the first distinct ones of the endpoint.
+    pub fn record_error(&self, endpoint: Endpoint, message: &str) {
+        self.endpoint(endpoint).sample(message);
+    }
+
+    pub fn record_timeout(&self, endpoint: Endpoint) {
+        self.timeouts[endpoint.index()].fetch_add(1, Relaxed);
+    }
+
+    pub fn record_hedge(&self, won: bool) {
+        let hedges = if won { &self.hedges_won } else { &self.hedges_lost };
+        hedges.fetch_add(1, Relaxed);
+    }
+
+    pub fn record_time(&self, activity: Activity, spent: Duration) {
+        self.times[activity.index()].fetch_add(spent.as_micros() as u64, Relaxed);
+    }
+
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    fn origin_stats(&self) -> OriginStats {
+        let endpoints = || Endpoint::ALL.iter().map(|e| self.endpoint(*e));
+        let sum = |counters: &[AtomicU64]| counters.iter().map(|c| c.load(Relaxed)).sum();
+        OriginStats {
+            requests: endpoints()
+                .flat_map(|m| m.histograms.iter())
+                .map(|h| h.count.load(Relaxed))
+                .sum(),
+            errors: endpoints().map(|m| sum(&m.statuses)).sum(),
+            timeouts: sum(&self.timeouts),
+            digs: sum(&self.digs_per_depth),
+            found: sum(&self.found_per_depth),
+            times: self.times(),
+        }
+    }
+
+    fn times(&self) -> WorkerTimes {
+        WorkerTimes {
+            wall_us: self.created.elapsed().as_micros() as u64,
+            spent_us: Activity::ALL
+                .iter()
+                .map(|a| (*a, self.times[a.index()].load(Relaxed)))
+                .collect(),
+        }
+    }
+
+    pub fn record_breaker(&self, endpoint: Endpoint, to: BreakerState) {
+        self.breaker[endpoint.index() * 3 + to.index()].fetch_add(1, Relaxed);
+    }
+}
+
+/// Everything recorded so far, merged across shards.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub explore: EndpointStats,
+    pub dig: EndpointStats,
+    pub cash: EndpointStats,
+    pub license: EndpointStats,
+    pub cash_at_depth: EndpointStats,
+    pub queue: EndpointStats,
+    /// digs made and digs that found treasure per depth
+    pub dig_found_per_depth: BTreeMap<u8, (f64, f64)>,
+    pub licenses_per_coins: BTreeMap<u64, u64>,
+    pub digs_allowed_total: u64,
+    pub timeouts: BTreeMap<Endpoint, u64>,
+    pub hedges: (u64, u64),
+    pub breaker: BTreeMap<(Endpoint, BreakerState), u64>,
+    pub limits: BTreeMap<Endpoint, u64>,
+    pub coins: u64,
+    pub active_licenses: u64,
+    pub treasures: u64,
+    pub origins: BTreeMap<Origin, OriginStats>,
+}
+
+impl Snapshot {
+    pub fn endpoint(&self, endpoint: Endpoint) -> &EndpointStats {
+        match endpoint {
+            Endpoint::Cash => &self.cash,
+            Endpoint::License => &self.license,
+            Endpoint::Dig => &self.dig,
+            Endpoint::Explore => &self.explore,
+        }
+    }
+
+    /// Counters grown since `earlier`, gauges and rate limits are the latest ones.
+    pub fn since(&self, earlier: &Snapshot) -> Snapshot {
+        let dig_found_per_depth = self
+            .dig_found_per_depth
+            .iter()
+            .map(|(depth, (digs, found))| {
+                let (d, f) = earlier.dig_found_per_depth.get(depth).unwrap_or(&(0., 0.));
+                (*depth, (digs - d, found - f))
+            })
+            .filter(|(_, (digs, _))| *digs > 0.)
+            .collect();
+        let origins = self
+            .origins
+            .iter()
+            .map(|(origin, stats)| match earlier.origins.get(origin) {
+                Some(e) => (*origin, stats.since(e)),
+                None => (*origin, stats.clone()),
+            })
+            .collect();
+        Snapshot {
+            explore: self.explore.since(&earlier.explore),
+            dig: self.dig.since(&earlier.dig),
+            cash: self.cash.since(&earlier.cash),
+            license: self.license.since(&earlier.license),
+            cash_at_depth: self.cash_at_depth.since(&earlier.cash_at_depth),
+            queue: self.queue.since(&earlier.queue),
+            dig_found_per_depth,
+            licenses_per_coins: counts_since(&self.licenses_per_coins, &earlier.licenses_per_coins),
+            digs_allowed_total: self.digs_allowed_total.saturating_sub(earlier.digs_allowed_total),
+            timeouts: counts_since(&self.timeouts, &earlier.timeouts),
+            hedges: (
+                self.hedges.0.saturating_sub(earlier.hedges.0),
+                self.hedges.1.saturating_sub(earlier.hedges.1),
+            ),
+            breaker: counts_since(&self.breaker, &earlier.breaker),
+            limits: self.limits.clone(),
+            coins: self.coins,
+            active_licenses: self.active_licenses,
+            treasures: self.treasures,
+            origins,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        Endpoint::ALL.iter().map(|e| self.endpoint(*e).total()).sum()
+    }
+
+    pub fn dig_found(&self) -> f64 {
+        self.dig_found_per_depth.values().map(|(_, found)| found).sum()
+    }
+
+    pub fn coins_earned(&self) -> u64 {
+        self.cash_at_depth.histograms.values().map(HistogramSnapshot::sum).sum()
+    }
+
+    pub fn coins_spent(&self) -> u64 {
+        self.licenses_per_coins.iter().map(|(coins, count)| coins * count).sum()
+    }
+
+    fn add(&mut self, metrics: &Metrics) {
+        self.explore.add(&metrics.explore);
+        self.dig.add(&metrics.dig);
+        self.cash.add(&metrics.cash);
+        self.license.add(&metrics.license);
+        self.cash_at_depth.add(&metrics.cash_at_depth);
+        self.queue.add(&metrics.queue);
+
+        let per_depth = metrics.digs_per_depth.iter().zip(metrics.found_per_depth.iter());
+        for (depth, (digs, found)) in per_depth.enumerate() {
+            let (digs, found) = (digs.load(Relaxed), found.load(Relaxed));
+            if digs > 0 {
+                let entry = self.dig_found_per_depth.entry(depth as u8).or_insert((0., 0.));
+                entry.0 += digs as f64;
+                entry.1 += found as f64;
+            }
+        }
+        for (coins, count) in metrics.licenses_per_coins.iter().enumerate() {
+            let count = count.load(Relaxed);
+            if count > 0 {
+                *self.licenses_per_coins.entry(coins as u64).or_insert(0) += count;
+            }
+        }
+        self.digs_allowed_total += metrics.digs_allowed.load(Relaxed);
+        for endpoint in Endpoint::ALL {
+            let timeouts = metrics.timeouts[endpoint.index()].load(Relaxed);
+            if timeouts > 0 {
+                *self.timeouts.entry(endpoint).or_insert(0) += timeouts;
+            }
+            for state in BreakerState::ALL {
+                let count = metrics.breaker[endpoint.index() * 3 + state.index()].load(Relaxed);
+                if count > 0 {
+                    *self.breaker.entry((endpoint, state)).or_insert(0) += count;
+                }
+            }
+        }
+        self.hedges.0 += metrics.hedges_won.load(Relaxed);
+        self.hedges.1 += metrics.hedges_lost.load(Relaxed);
+        self.origins.insert(metrics.origin, metrics.origin_stats());
+    }
+}
+
+/// Hands out a metrics shard to every worker and merges them on demand.
+pub struct Registry {
+    shards: Mutex<Vec<Arc<Metrics>>>,
+    gauges: Arc<Gauges>,
+}
+
+impl Registry {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            shards: Mutex::new(vec![]),
+            gauges: Arc::new(Gauges::new()),
+        })
+    }
+
+    /// Every actor is expected to ask for a single shard, totals per origin are kept apart.
+    pub fn shard(&self, origin: Origin) -> Arc<Metrics> {
+        let shard = Arc::new(Metrics::new(self.gauges.clone(), origin));
+        self.shards
+            .lock()
+            .expect("metrics registry lock poisoned")
+            .push(shard.clone());
+        shard
+    }
+
+    fn shards(&self) -> Vec<Arc<Metrics>> {
+        self.shards
+            .lock()
+            .expect("metrics registry lock poisoned")
+            .clone()
+    }
+
+    /// Merges only the latencies and statuses of one endpoint.
+    pub fn endpoint(&self, endpoint: Endpoint) -> EndpointStats {
+        let mut stats = EndpointStats::default();
+        for shard in self.shards().iter() {
+            stats.add(shard.endpoint(endpoint));
+        }
+        stats
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let shards = self.shards();
+        let mut snapshot = Snapshot::default();
+        for shard in shards.iter() {
+            snapshot.add(shard);
+        }
+        for endpoint in Endpoint::ALL {
+            let limit = self.gauges.limits[endpoint.index()].load(Relaxed);
+            if limit > 0 {
+                snapshot.limits.insert(endpoint, limit);
+            }
+        }
+        snapshot.coins = self.gauges.coins.load(Relaxed);
+        snapshot.active_licenses = self.gauges.active_licenses.load(Relaxed);
+        snapshot.treasures = self.gauges.treasures.load(Relaxed);
+        snapshot
+    }
+}
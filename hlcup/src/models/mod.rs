@@ -1,2 +1,16 @@
 pub mod messages;
-pub mod data;
\ No newline at end of file
+pub mod data;
+pub mod planner;
+pub mod cost;
+pub mod depth;
+pub mod density;
+pub mod clusters;
+pub mod bandit;
+pub mod priority;
+pub mod task;
+pub mod ledger;
+pub mod metrics;
+pub mod report;
+pub mod timeline;
+pub mod trace;
+pub mod heatmap;
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::constants::{
+    DEFAULT_CASH_US, DEFAULT_DIG_US, DEFAULT_EXPLORE_US, MAX_SPLIT_LEVELS, PLANNER_REFRESH_MS,
+    TIME_LIMIT_MS,
+};
+use crate::http::dto::Explore;
+use crate::models::cost::ExploreCostModel;
+use crate::models::depth::DepthModel;
+use crate::models::metrics::{Registry, Snapshot};
+
+/// Latencies and models measured so far, latencies are `None` until an endpoint was called.
+#[derive(Debug, Default, Clone)]
+pub struct Measurements {
+    pub explore_us: Option<f64>,
+    pub explore_cost: ExploreCostModel,
+    pub dig_us: Option<f64>,
+    pub cash_us: Option<f64>,
+    /// mean depth at which treasures were found
+    pub digs_per_treasure: Option<f64>,
+    pub depth_model: DepthModel,
+}
+
+impl Measurements {
+    pub fn new(snapshot: &Snapshot) -> Self {
+        let (depth_sum, found) = snapshot
+            .dig_found_per_depth
+            .iter()
+            .fold((0., 0.), |(sum, count), (depth, (_, found))| {
+                (sum + *depth as f64 * found, count + found)
+            });
+        Self {
+            explore_us: snapshot.explore.mean(),
+            explore_cost: ExploreCostModel::new(snapshot.explore.means()),
+            dig_us: snapshot.dig.mean(),
+            cash_us: snapshot.cash.mean(),
+            digs_per_treasure: if found > 0. { Some(depth_sum / found) } else { None },
+            depth_model: DepthModel::new(
+                &snapshot.dig_found_per_depth,
+                snapshot
+                    .cash_at_depth
+                    .means()
+                    .into_iter()
+                    .map(|(depth, coins, _)| (depth, coins)),
+            ),
+        }
+    }
+}
+
+/// Keeps track of the time left in the game and what can still be done in it,
+/// using measurements taken from snapshots of the metrics registry.
+pub struct Planner {
+    started: Instant,
+    refreshed: Option<Instant>,
+    measured: Measurements,
+    registry: Arc<Registry>,
+}
+
+impl Planner {
+    pub fn new(started: Instant, registry: Arc<Registry>) -> Self {
+        Self {
+            started,
+            refreshed: None,
+            measured: Measurements::default(),
+            registry,
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        if let Some(refreshed) = self.refreshed {
+            if refreshed.elapsed().as_millis() < PLANNER_REFRESH_MS {
+                return;
+            }
+        }
+        self.measured = Measurements::new(&self.registry.snapshot());
+        self.refreshed = Some(Instant::now());
+    }
+
+    pub fn remaining_us(&self) -> f64 {
+        TIME_LIMIT_MS.saturating_sub(self.started.elapsed().as_millis()) as f64 * 1000.
+    }
+
+    pub fn depth_model(&self) -> &DepthModel {
+        &self.measured.depth_model
+    }
+
+    pub fn explore_us(&self, size: u64) -> f64 {
+        self.measured
+            .explore_cost
+            .cost_us(size)
+            .or(self.measured.explore_us)
+            .unwrap_or(DEFAULT_EXPLORE_US)
+    }
+
+    pub fn dig_us(&self) -> f64 {
+        self.measured.dig_us.unwrap_or(DEFAULT_DIG_US)
+    }
+
+    pub fn cash_us(&self) -> f64 {
+        self.measured.cash_us.unwrap_or(DEFAULT_CASH_US)
+    }
+
+    /// Digs a single worker can still make, leaving time to cash the last treasure.
+    pub fn dig_capacity(&self) -> u64 {
+        ((self.remaining_us() - self.cash_us()).max(0.) / self.dig_us()) as u64
+    }
+
+    pub fn digs_per_treasure(&self, max_depth: u8) -> f64 {
+        self.measured
+            .digs_per_treasure
+            .unwrap_or(max_depth as f64 / 3.)
+    }
+
+    /// Time needed to narrow down every treasure in the area to its cell and dig it out.
+    pub fn cost_us(&self, explore: &Explore, max_depth: u8) -> f64 {
+        let treasures = explore.amount.min(explore.area.size()) as f64;
+        let (_, narrowing_us) = self.narrowing(explore)[0];
+        narrowing_us + treasures * self.digs_per_treasure(max_depth) * self.dig_us()
+    }
+
+    /// Quadtree levels to split the area by at once, the fewest explores
+    /// for the treasures in it for what they cost at the sizes measured.
+    pub fn split_levels(&self, explore: &Explore) -> u32 {
+        self.narrowing(explore)[0].0.max(1)
+    }
+
+    // For every quadtree level down to single cells: levels to split a part of it by at once
+    // and the time to narrow down the treasures in that part. Treasures are spread over
+    // as many parts as there are of them and the last part of a split is inferred.
+    fn narrowing(&self, explore: &Explore) -> Vec<(u32, f64)> {
+        let size = explore.area.size();
+        let treasures = explore.amount.min(size) as f64;
+        let mut sizes = vec![size];
+        while *sizes.last().unwrap() > 1 {
+            sizes.push(sizes.last().unwrap().div_ceil(4));
+        }
+        let treasures_at = |level: usize| (treasures / 4f64.powi(level as i32)).max(1.);
+
+        let mut levels = vec![(0, 0.); sizes.len()];
+        if treasures == 0. {
+            return levels;
+        }
+        for level in (0..sizes.len() - 1).rev() {
+            let deepest = (level + MAX_SPLIT_LEVELS as usize).min(sizes.len() - 1);
+            levels[level] = (level + 1..=deepest)
+                .map(|below| {
+                    let parts = 4f64.powi((below - level) as i32);
+                    let occupied = treasures_at(level).min(parts);
+                    let explores_us = (parts - 1.) * self.explore_us(sizes[below]);
+                    ((below - level) as u32, explores_us + occupied * levels[below].1)
+                })
+                .fold((0, f64::INFINITY), |best, split| {
+                    if split.1 < best.1 {
+                        split
+                    } else {
+                        best
+                    }
+                });
+        }
+        levels
+    }
+
+    /// Time needed to dig every cell of the area without exploring it any further,
+    /// when each cell holds a treasure with `probability`. Empty cells are dug to the bottom.
+    pub fn blind_dig_cost_us(&self, explore: &Explore, probability: f64, max_depth: u8) -> f64 {
+        let digs_per_cell =
+            probability * self.digs_per_treasure(max_depth) + (1. - probability) * max_depth as f64;
+        explore.area.size() as f64 * digs_per_cell * self.dig_us()
+    }
+
+    pub fn is_managable(&self, explore: &Explore, max_depth: u8) -> bool {
+        self.cost_us(explore, max_depth) < self.remaining_us()
+    }
+}
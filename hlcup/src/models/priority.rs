@@ -0,0 +1,139 @@
+use std::cmp::Ordering;
+
+use crate::http::dto::Area;
+
+// precision of scores built from floating point estimates
+const FIXED_SCALE: u128 = 1_000_000_000;
+
+/// Exact priority: a non-negative fraction compared without rounding or overflow,
+/// with a tie-break key deciding between equal fractions.
+#[derive(Debug, Clone, Copy)]
+pub struct Score {
+    num: u128,
+    den: u128,
+    tie: u128,
+}
+
+impl Score {
+    pub fn ratio(num: u128, den: u128) -> Self {
+        Self {
+            num,
+            den: den.max(1),
+            tie: 0,
+        }
+    }
+
+    /// Floating point estimates are kept with `FIXED_SCALE` precision.
+    pub fn fixed(value: f64) -> Self {
+        Self::ratio((value.max(0.) * FIXED_SCALE as f64).round() as u128, FIXED_SCALE)
+    }
+
+    /// Divides the fraction by `attempts + 1`, the tie-break is kept.
+    pub fn demoted(self, attempts: u32) -> Self {
+        Self {
+            den: self.den * (attempts as u128 + 1),
+            ..self
+        }
+    }
+
+    /// Higher ties win between equal fractions.
+    pub fn then(self, tie: u128) -> Self {
+        Self { tie, ..self }
+    }
+}
+
+/// Compares `a / b` with `c / d` by their continued fraction expansions.
+fn cmp_fractions(mut a: u128, mut b: u128, mut c: u128, mut d: u128) -> Ordering {
+    loop {
+        let (q1, r1) = (a / b, a % b);
+        let (q2, r2) = (c / d, c % d);
+        if q1 != q2 {
+            return q1.cmp(&q2);
+        }
+        match (r1, r2) {
+            (0, 0) => return Ordering::Equal,
+            (0, _) => return Ordering::Less,
+            (_, 0) => return Ordering::Greater,
+            // r1 / b < r2 / d exactly when d / r2 < b / r1
+            _ => {
+                (a, b, c, d) = (d, r2, b, r1);
+            }
+        }
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_fractions(self.num, self.den, other.num, other.den).then(self.tie.cmp(&other.tie))
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+/// Tie-break favouring smaller areas, then the ones closer to the origin.
+pub fn area_tie(area: &Area) -> u128 {
+    ((u64::MAX - area.size()) as u128) << 64 | cell_tie(area.pos_x, area.pos_y)
+}
+
+/// Tie-break favouring cells closer to the origin.
+pub fn cell_tie(x: u64, y: u64) -> u128 {
+    (u32::MAX as u128 - x as u128) << 32 | (u32::MAX as u128 - y as u128)
+}
+
+/// Heap entry ordered by the score computed for its item when it was queued.
+#[derive(Debug)]
+pub struct Prioritized<T> {
+    score: Score,
+    item: T,
+}
+
+impl<T> Prioritized<T> {
+    pub fn new(item: T, score: impl FnOnce(&T) -> Score) -> Self {
+        let score = score(&item);
+        Self { score, item }
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    pub fn item(&self) -> &T {
+        &self.item
+    }
+
+    pub fn into_inner(self) -> T {
+        self.item
+    }
+}
+
+impl<T> PartialEq for Prioritized<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
+    }
+}
+
+impl<T> Eq for Prioritized<T> {}
+
+impl<T> Ord for Prioritized<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.cmp(&other.score)
+    }
+}
+
+impl<T> PartialOrd for Prioritized<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
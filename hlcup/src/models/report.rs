@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::http::breaker::BreakerState;
+use crate::http::scheduler::Endpoint;
+use crate::models::metrics::{EndpointStats, HistogramSnapshot, OriginStats, Snapshot};
+
+/// Bumped whenever a field is renamed or removed, new fields may appear without it.
+pub const REPORT_SCHEMA: u32 = 2;
+
+/// Latency summary of one histogram key, all values in microseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyReport {
+    pub count: u64,
+    pub min: u64,
+    pub mean: f64,
+    pub max: u64,
+    pub stddev: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+}
+
+impl LatencyReport {
+    fn new(histogram: &HistogramSnapshot) -> Self {
+        Self {
+            count: histogram.entries(),
+            min: histogram.minimum().unwrap_or(0),
+            mean: histogram.mean().unwrap_or(0.),
+            max: histogram.maximum().unwrap_or(0),
+            stddev: histogram.stddev().unwrap_or(0.),
+            p50: histogram.percentile(50.0).unwrap_or(0),
+            p90: histogram.percentile(90.0).unwrap_or(0),
+            p99: histogram.percentile(99.0).unwrap_or(0),
+            p999: histogram.percentile(99.9).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EndpointReport {
+    pub requests: u64,
+    pub errors: u64,
+    /// error responses per status code
+    pub statuses: BTreeMap<String, u64>,
+    /// keyed by log2 size bucket for explores, by depth for digs and cashes
+    pub latency_us: BTreeMap<String, LatencyReport>,
+    /// the first distinct error messages with how often each was seen
+    pub error_samples: BTreeMap<String, u64>,
+}
+
+impl EndpointReport {
+    fn new(stats: &EndpointStats) -> Self {
+        Self {
+            requests: stats.total(),
+            errors: stats.errors(),
+            statuses: stats
+                .statuses
+                .iter()
+                .map(|(status, count)| (status.to_string(), *count))
+                .collect(),
+            latency_us: stats
+                .histograms
+                .iter()
+                .map(|(key, h)| (key.to_string(), LatencyReport::new(h)))
+                .collect(),
+            error_samples: stats.samples.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepthReport {
+    pub digs: u64,
+    pub found: u64,
+    pub found_rate: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CellsReport {
+    pub dug: u64,
+    pub digs: u64,
+    pub failed: u64,
+}
+
+/// Totals of one actor, wall-clock time split by activity with unattributed time in `other`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OriginReport {
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub timeouts: u64,
+    pub digs: u64,
+    pub found: u64,
+    pub found_rate: f64,
+    pub wall_us: u64,
+    pub spent_us: BTreeMap<String, u64>,
+}
+
+impl OriginReport {
+    fn new(stats: &OriginStats) -> Self {
+        let times = &stats.times;
+        let mut spent_us = times
+            .spent_us
+            .iter()
+            .map(|(activity, us)| (activity.name().to_string(), *us))
+            .collect::<BTreeMap<String, u64>>();
+        spent_us.insert("other".to_string(), times.other_us());
+        Self {
+            requests: stats.requests,
+            errors: stats.errors,
+            error_rate: stats.error_rate(),
+            timeouts: stats.timeouts,
+            digs: stats.digs,
+            found: stats.found,
+            found_rate: stats.found_rate(),
+            wall_us: times.wall_us,
+            spent_us,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HedgeReport {
+    pub won: u64,
+    pub lost: u64,
+}
+
+/// Stable JSON view of `Stats`, written to disk to archive runs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub schema: u32,
+    pub timestamp_ms: u64,
+    pub total: u64,
+    pub endpoints: BTreeMap<String, EndpointReport>,
+    pub queue_delay_us: BTreeMap<String, LatencyReport>,
+    /// coins received per cash, keyed by depth
+    pub cash_at_depth: BTreeMap<String, LatencyReport>,
+    pub dig_depths: BTreeMap<String, DepthReport>,
+    pub licenses_per_coins: BTreeMap<String, u64>,
+    pub digs_allowed_total: u64,
+    pub cells: CellsReport,
+    pub timeouts: BTreeMap<String, u64>,
+    pub hedges: HedgeReport,
+    /// transitions into each state per endpoint
+    pub breaker: BTreeMap<String, BTreeMap<String, u64>>,
+    pub limits: BTreeMap<String, u64>,
+    pub coins: u64,
+    pub active_licenses: u64,
+    /// keyed by `accounting` and `worker-<index>`
+    pub origins: BTreeMap<String, OriginReport>,
+}
+
+impl Report {
+    pub fn new(snapshot: &Snapshot, cells: CellsReport, timestamp_ms: u64) -> Self {
+        let per_endpoint = |values: &BTreeMap<Endpoint, u64>| {
+            values
+                .iter()
+                .map(|(e, v)| (e.name().to_string(), *v))
+                .collect::<BTreeMap<String, u64>>()
+        };
+        Self {
+            schema: REPORT_SCHEMA,
+            timestamp_ms,
+            total: snapshot.total(),
+            endpoints: Endpoint::ALL
+                .iter()
+                .map(|e| (e.name().to_string(), EndpointReport::new(snapshot.endpoint(*e))))
+                .collect(),
+            queue_delay_us: snapshot
+                .queue
+                .histograms
+                .iter()
+                .filter_map(|(index, h)| {
+                    let endpoint = Endpoint::ALL.get(*index as usize)?;
+                    Some((endpoint.name().to_string(), LatencyReport::new(h)))
+                })
+                .collect(),
+            cash_at_depth: snapshot
+                .cash_at_depth
+                .histograms
+                .iter()
+                .map(|(depth, h)| (depth.to_string(), LatencyReport::new(h)))
+                .collect(),
+            dig_depths: snapshot
+                .dig_found_per_depth
+                .iter()
+                .map(|(depth, (digs, found))| {
+                    let report = DepthReport {
+                        digs: *digs as u64,
+                        found: *found as u64,
+                        found_rate: found / digs,
+                    };
+                    (depth.to_string(), report)
+                })
+                .collect(),
+            licenses_per_coins: snapshot
+                .licenses_per_coins
+                .iter()
+                .map(|(coins, count)| (coins.to_string(), *count))
+                .collect(),
+            digs_allowed_total: snapshot.digs_allowed_total,
+            cells,
+            timeouts: per_endpoint(&snapshot.timeouts),
+            hedges: HedgeReport {
+                won: snapshot.hedges.0,
+                lost: snapshot.hedges.1,
+            },
+            breaker: Endpoint::ALL
+                .iter()
+                .map(|e| {
+                    let states = BreakerState::ALL
+                        .iter()
+                        .map(|s| {
+                            let count = snapshot.breaker.get(&(*e, *s)).copied().unwrap_or(0);
+                            (s.name().to_string(), count)
+                        })
+                        .collect();
+                    (e.name().to_string(), states)
+                })
+                .collect(),
+            limits: per_endpoint(&snapshot.limits),
+            coins: snapshot.coins,
+            active_licenses: snapshot.active_licenses,
+            origins: snapshot
+                .origins
+                .iter()
+                .map(|(origin, stats)| (origin.to_string(), OriginReport::new(stats)))
+                .collect(),
+        }
+    }
+}
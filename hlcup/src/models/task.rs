@@ -0,0 +1,66 @@
+use crate::constants::MAX_ATTEMPTS;
+use crate::http::dto::{Area, Explore};
+use crate::models::priority::Score;
+use crate::models::trace::{next_trace, TraceId};
+
+/// Unit of work taken off a heap. When a step fails it goes back with
+/// its attempt count bumped, until `MAX_ATTEMPTS` is reached.
+#[derive(Debug)]
+pub struct Job<T> {
+    pub task: T,
+    pub attempts: u32,
+}
+
+impl<T> Job<T> {
+    pub fn new(task: T) -> Self {
+        Self { task, attempts: 0 }
+    }
+
+    /// `None` once the job failed too many times to be tried again.
+    pub fn retry(self) -> Option<Self> {
+        if self.attempts + 1 >= MAX_ATTEMPTS {
+            return None;
+        }
+        Some(Self {
+            attempts: self.attempts + 1,
+            ..self
+        })
+    }
+
+    /// Every failed attempt lowers the job's score so other work runs first.
+    pub fn priority(&self, score: Score) -> Score {
+        score.demoted(self.attempts)
+    }
+}
+
+/// Area being narrowed down to smaller ones. Parts explored before a failure
+/// are not explored again, only the `remaining` ones are.
+#[derive(Debug)]
+pub struct Narrowing {
+    pub explore: Explore,
+    /// parts left after a failed attempt, the last one is inferred and never explored
+    pub remaining: Option<Vec<Area>>,
+    /// treasures found in the parts explored so far
+    pub found: u64,
+    pub trace: TraceId,
+}
+
+impl Narrowing {
+    /// Every area gets its own trace id.
+    pub fn new(explore: Explore) -> Self {
+        Self {
+            explore,
+            remaining: None,
+            found: 0,
+            trace: next_trace(),
+        }
+    }
+
+    /// Keeps the parts from `failed` on for the next attempt.
+    pub fn suspend(&mut self, mut parts: Vec<Area>, failed: usize, last: Option<Area>, found: u64) {
+        let mut remaining = parts.split_off(failed);
+        remaining.extend(last);
+        self.remaining = Some(remaining);
+        self.found = found;
+    }
+}
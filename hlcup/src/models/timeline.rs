@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::http::scheduler::Endpoint;
+use crate::models::metrics::Snapshot;
+
+/// Requests of one endpoint answered within a window, latencies in microseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowRates {
+    pub requests: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+}
+
+/// Difference between two consecutive samples, gauges are taken at the end of the window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Window {
+    pub start_ms: u64,
+    pub duration_ms: u64,
+    pub endpoints: BTreeMap<String, WindowRates>,
+    pub treasures_found: u64,
+    pub coins_earned: u64,
+    pub coins_spent: u64,
+    pub digs_allowed: u64,
+    pub coins: u64,
+    pub treasures: u64,
+    pub active_licenses: u64,
+}
+
+impl Window {
+    fn new(earlier: &Snapshot, now: &Snapshot, start_ms: u64, duration_ms: u64) -> Self {
+        let endpoints = Endpoint::ALL
+            .iter()
+            .map(|e| {
+                let (before, after) = (earlier.endpoint(*e), now.endpoint(*e));
+                let latency = after.merged().since(&before.merged());
+                let timeouts = |s: &Snapshot| s.timeouts.get(e).copied().unwrap_or(0);
+                let rates = WindowRates {
+                    requests: after.total() - before.total(),
+                    errors: after.errors() - before.errors(),
+                    timeouts: timeouts(now) - timeouts(earlier),
+                    p50_us: latency.percentile(50.0).unwrap_or(0),
+                    p90_us: latency.percentile(90.0).unwrap_or(0),
+                    p99_us: latency.percentile(99.0).unwrap_or(0),
+                };
+                (e.name().to_string(), rates)
+            })
+            .collect();
+        Self {
+            start_ms,
+            duration_ms,
+            endpoints,
+            treasures_found: (now.dig_found() - earlier.dig_found()) as u64,
+            coins_earned: now.coins_earned() - earlier.coins_earned(),
+            coins_spent: now.coins_spent() - earlier.coins_spent(),
+            digs_allowed: now.digs_allowed_total - earlier.digs_allowed_total,
+            coins: now.coins,
+            treasures: now.treasures,
+            active_licenses: now.active_licenses,
+        }
+    }
+}
+
+/// Per-window view of the game built from registry snapshots taken at a steady pace.
+pub struct Timeline {
+    started: Instant,
+    last: Option<(u64, Snapshot)>,
+    windows: Vec<Window>,
+}
+
+impl Timeline {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            last: None,
+            windows: vec![],
+        }
+    }
+
+    pub fn sample(&mut self, snapshot: Snapshot) {
+        self.sample_at(self.started.elapsed().as_millis() as u64, snapshot)
+    }
+
+    /// The first sample only opens a window.
+    pub fn sample_at(&mut self, elapsed_ms: u64, snapshot: Snapshot) {
+        if let Some((start_ms, earlier)) = &self.last {
+            let window = Window::new(earlier, &snapshot, *start_ms, elapsed_ms - start_ms);
+            self.windows.push(window);
+        }
+        self.last = Some((elapsed_ms, snapshot));
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(&self.windows)
+    }
+
+    /// One row per window, endpoint columns are prefixed with the endpoint name.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("start_ms,duration_ms");
+        for e in Endpoint::ALL.iter() {
+            for column in ["requests", "errors", "timeouts", "p50_us", "p90_us", "p99_us"].iter() {
+                let _ = write!(out, ",{}_{}", e.name(), column);
+            }
+        }
+        out.push_str(
+            ",treasures_found,coins_earned,coins_spent,digs_allowed,coins,treasures,active_licenses\n",
+        );
+        for w in self.windows.iter() {
+            let _ = write!(out, "{},{}", w.start_ms, w.duration_ms);
+            for e in Endpoint::ALL.iter() {
+                let r = &w.endpoints[e.name()];
+                let _ = write!(
+                    out,
+                    ",{},{},{},{},{},{}",
+                    r.requests, r.errors, r.timeouts, r.p50_us, r.p90_us, r.p99_us
+                );
+            }
+            let _ = writeln!(
+                out,
+                ",{},{},{},{},{},{},{}",
+                w.treasures_found,
+                w.coins_earned,
+                w.coins_spent,
+                w.digs_allowed,
+                w.coins,
+                w.treasures,
+                w.active_licenses
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,11 @@
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+/// Correlation id of an area with treasure, handed down to the digs in it
+/// and the treasures they bring up so a treasure can be followed up to its cash.
+pub type TraceId = u64;
+
+static NEXT_TRACE: AtomicU64 = AtomicU64::new(1);
+
+pub fn next_trace() -> TraceId {
+    NEXT_TRACE.fetch_add(1, Relaxed)
+}
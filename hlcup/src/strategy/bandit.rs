@@ -0,0 +1,53 @@
+use crate::http::dto::{Area, Explore};
+use crate::models::data::PendingDig;
+use crate::models::priority::Score;
+use crate::strategy::density::Density;
+use crate::strategy::{Context, Strategy};
+
+pub const NAME: &str = "bandit";
+
+/// Spreads explores over regions with UCB1 on coins per request,
+/// everything else is decided the same way as `Density` does.
+pub struct Ucb;
+
+impl Strategy for Ucb {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn pick_region(&self, tops: &[(usize, Score)], ctx: &Context) -> Option<usize> {
+        ctx.bandit.choose(tops.iter().map(|(region, _)| *region))
+    }
+
+    fn explore_score(&self, explore: &Explore, ctx: &Context) -> Score {
+        Density.explore_score(explore, ctx)
+    }
+
+    fn dig_score(&self, dig: &PendingDig, ctx: &Context) -> Score {
+        Density.dig_score(dig, ctx)
+    }
+
+    fn split(&self, explore: &Explore, ctx: &Context) -> Vec<Area> {
+        Density.split(explore, ctx)
+    }
+
+    fn blind_dig(&self, explore: &Explore, ctx: &Context) -> bool {
+        Density.blind_dig(explore, ctx)
+    }
+
+    fn is_managable(&self, explore: &Explore, ctx: &Context) -> bool {
+        Density.is_managable(explore, ctx)
+    }
+
+    fn next_level(&self, dig: &PendingDig, ctx: &Context, excavated: u64) -> Option<PendingDig> {
+        Density.next_level(dig, ctx, excavated)
+    }
+
+    fn licenses_to_prep(&self, active_licenses: u8, max_concurrent_licenses: u8) -> u8 {
+        Density.licenses_to_prep(active_licenses, max_concurrent_licenses)
+    }
+
+    fn coins_for_license(&self, coins: &mut Vec<u64>) -> Vec<u64> {
+        Density.coins_for_license(coins)
+    }
+}
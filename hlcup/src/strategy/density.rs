@@ -0,0 +1,112 @@
+use crate::http::dto::{Area, Explore};
+use crate::models::data::PendingDig;
+use crate::models::priority::{area_tie, cell_tie, Score};
+use crate::strategy::{Context, Strategy};
+
+pub const NAME: &str = "density";
+
+// digs expected to bring less than this are not worth a license use
+const MIN_COINS_PER_DIG: f64 = 0.05;
+// larger areas are never dug blindly, no matter what the model says
+const MAX_BLIND_DIG_SIZE: u64 = 16;
+// how much treasures found nearby raise the score of an area
+const CLUSTER_WEIGHT: f64 = 0.5;
+// precision of the cluster boost
+const BOOST_SCALE: u128 = 1000;
+
+/// Explores the densest areas first, favouring the ones next to cells where
+/// treasure was already found, digs the levels expected to bring
+/// the most coins first and spends one coin per license while it has any.
+pub struct Density;
+
+impl Strategy for Density {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn pick_region(&self, tops: &[(usize, Score)], _ctx: &Context) -> Option<usize> {
+        // the best area overall, as if all regions shared one queue
+        tops.iter()
+            .fold(None, |best: Option<&(usize, Score)>, top| match best {
+                Some(b) if b.1 >= top.1 => Some(b),
+                _ => Some(top),
+            })
+            .map(|(region, _)| *region)
+    }
+
+    fn explore_score(&self, explore: &Explore, ctx: &Context) -> Score {
+        let hits = ctx.hits.neighbours(&explore.area) as f64;
+        let boost = (CLUSTER_WEIGHT * hits.ln_1p() * BOOST_SCALE as f64) as u128;
+        let scaled = BOOST_SCALE + boost;
+        let size = explore.area.size() as u128 * BOOST_SCALE;
+        match explore.amount {
+            // areas that could not be explored rank by the density seen so far
+            u64::MAX => {
+                let amount = ctx.density.expected_amount(explore);
+                Score::fixed(amount * scaled as f64 / size as f64)
+            }
+            amount => Score::ratio(amount as u128 * scaled, size),
+        }
+        .then(area_tie(&explore.area))
+    }
+
+    fn dig_score(&self, dig: &PendingDig, ctx: &Context) -> Score {
+        let coins = ctx
+            .planner
+            .depth_model()
+            .expected_coins(dig.depth, dig.remaining, ctx.max_depth);
+        Score::fixed(coins).then(cell_tie(dig.x, dig.y))
+    }
+
+    fn split(&self, explore: &Explore, ctx: &Context) -> Vec<Area> {
+        let levels = ctx.planner.split_levels(&expected(explore, ctx));
+        explore.area.clone().split_levels(levels)
+    }
+
+    fn blind_dig(&self, explore: &Explore, ctx: &Context) -> bool {
+        let size = explore.area.size();
+        if size <= 1 || size > MAX_BLIND_DIG_SIZE {
+            return false;
+        }
+        let probability = ctx.density.cell_probability(explore);
+        ctx.planner
+            .blind_dig_cost_us(explore, probability, ctx.max_depth)
+            < ctx.planner.cost_us(&expected(explore, ctx), ctx.max_depth)
+    }
+
+    fn is_managable(&self, explore: &Explore, ctx: &Context) -> bool {
+        ctx.planner.is_managable(&expected(explore, ctx), ctx.max_depth)
+    }
+
+    fn next_level(&self, dig: &PendingDig, ctx: &Context, excavated: u64) -> Option<PendingDig> {
+        if ctx.planner.dig_capacity() == 0 {
+            return None;
+        }
+        dig.next_level(ctx.max_depth, excavated).filter(|next| {
+            ctx.planner
+                .depth_model()
+                .expected_coins(next.depth, next.remaining, ctx.max_depth)
+                >= MIN_COINS_PER_DIG
+        })
+    }
+
+    fn licenses_to_prep(&self, active_licenses: u8, max_concurrent_licenses: u8) -> u8 {
+        max_concurrent_licenses - active_licenses
+    }
+
+    fn coins_for_license(&self, coins: &mut Vec<u64>) -> Vec<u64> {
+        if let Some(c) = coins.pop() {
+            vec![c]
+        } else {
+            vec![]
+        }
+    }
+}
+
+// the area with its amount, or the posterior estimate of it when unknown
+fn expected(explore: &Explore, ctx: &Context) -> Explore {
+    Explore {
+        area: explore.area.clone(),
+        amount: ctx.density.expected_amount(explore).round() as u64,
+    }
+}
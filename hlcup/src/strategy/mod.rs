@@ -0,0 +1,102 @@
+pub mod bandit;
+pub mod density;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::http::dto::{Area, Explore};
+use crate::models::bandit::Bandit;
+use crate::models::clusters::HitIndex;
+use crate::models::data::PendingDig;
+use crate::models::density::DensityModel;
+use crate::models::metrics::Registry;
+use crate::models::planner::Planner;
+use crate::models::priority::Score;
+use crate::Rules;
+
+pub const DEFAULT: &str = density::NAME;
+
+pub type SharedStrategy = Arc<dyn Strategy + Send + Sync>;
+
+/// Models a worker keeps learning from as the game goes.
+pub struct Knowledge {
+    pub planner: Planner,
+    pub density: DensityModel,
+    pub hits: HitIndex,
+    pub bandit: Bandit,
+}
+
+impl Knowledge {
+    pub fn new(
+        started: Instant,
+        registry: Arc<Registry>,
+        rules: &Rules,
+        regions: Vec<Area>,
+    ) -> Self {
+        Self {
+            planner: Planner::new(started, registry),
+            density: DensityModel::new(),
+            hits: HitIndex::new(rules.clustering),
+            bandit: Bandit::new(regions),
+        }
+    }
+
+    pub fn context(&self, max_depth: u8) -> Context<'_> {
+        Context {
+            planner: &self.planner,
+            density: &self.density,
+            hits: &self.hits,
+            bandit: &self.bandit,
+            max_depth,
+        }
+    }
+}
+
+/// What a worker knows at the moment a decision is made.
+pub struct Context<'a> {
+    pub planner: &'a Planner,
+    pub density: &'a DensityModel,
+    pub hits: &'a HitIndex,
+    pub bandit: &'a Bandit,
+    pub max_depth: u8,
+}
+
+/// Every decision the bot makes about where to explore, how to dig
+/// and how to pay for licenses. Implementations are picked by name
+/// at startup (see `from_name`) so they can be compared on the same build.
+pub trait Strategy {
+    fn name(&self) -> &'static str;
+
+    /// Region to explore next, given the score of the best area queued in each non-empty one.
+    fn pick_region(&self, tops: &[(usize, Score)], ctx: &Context) -> Option<usize>;
+
+    /// Higher scores are explored first within a region.
+    fn explore_score(&self, explore: &Explore, ctx: &Context) -> Score;
+
+    /// Higher scores are dug first.
+    fn dig_score(&self, dig: &PendingDig, ctx: &Context) -> Score;
+
+    /// Parts to explore next when narrowing down the area.
+    fn split(&self, explore: &Explore, ctx: &Context) -> Vec<Area>;
+
+    /// Whether to dig every cell of the area right away instead of exploring it further.
+    fn blind_dig(&self, explore: &Explore, ctx: &Context) -> bool;
+
+    fn is_managable(&self, explore: &Explore, ctx: &Context) -> bool;
+
+    fn next_level(&self, dig: &PendingDig, ctx: &Context, excavated: u64) -> Option<PendingDig>;
+
+    /// How many new licenses to request given how many are still active.
+    fn licenses_to_prep(&self, active_licenses: u8, max_concurrent_licenses: u8) -> u8;
+
+    /// Coins to pay for a single license, taken out of the wallet.
+    fn coins_for_license(&self, coins: &mut Vec<u64>) -> Vec<u64>;
+}
+
+pub fn from_name(name: &str) -> Option<SharedStrategy> {
+    match name {
+        density::NAME => Some(Arc::new(density::Density)),
+        bandit::NAME => Some(Arc::new(bandit::Ucb)),
+        _ => None,
+    }
+}
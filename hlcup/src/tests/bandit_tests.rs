@@ -0,0 +1,27 @@
+use crate::http::dto::Area;
+use crate::models::bandit::Bandit;
+
+#[test]
+fn test_bandit_ucb() {
+    let mut bandit = Bandit::new(vec![
+        Area { pos_x: 0, pos_y: 0, size_x: 10, size_y: 10 },
+        Area { pos_x: 10, pos_y: 0, size_x: 10, size_y: 10 },
+        Area { pos_x: 20, pos_y: 0, size_x: 10, size_y: 10 },
+    ]);
+
+    assert_eq!(bandit.region_of(15, 3), 1);
+    assert_eq!(bandit.region_of(100, 100), 0);
+    assert_eq!(bandit.choose(vec![1, 2]), Some(1));
+
+    for _ in 0..100 {
+        bandit.record_request(0);
+        bandit.record_request(1);
+        bandit.record_request(2);
+    }
+    bandit.record_coins(2, 50.);
+    bandit.record_coins(0, 10.);
+
+    assert_eq!(bandit.choose(0..3), Some(2));
+    assert_eq!(bandit.choose(vec![0, 1]), Some(0));
+    assert_eq!(bandit.choose(vec![]), None);
+}
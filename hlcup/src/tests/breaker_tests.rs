@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use crate::http::breaker::{Breaker, BreakerState, Transition};
+
+#[test]
+fn test_breaker_opens_and_probes() {
+    let breaker = Breaker::new();
+    for _ in 0..4 {
+        assert!(breaker.enter().0.is_ok());
+        assert_eq!(breaker.record(false), None);
+    }
+    assert_eq!(
+        breaker.record(false),
+        Some(Transition {
+            from: BreakerState::Closed,
+            to: BreakerState::Open
+        })
+    );
+    // fails fast while open
+    assert!(breaker.enter().0.is_err());
+
+    std::thread::sleep(Duration::from_millis(110));
+    let (entered, transition) = breaker.enter();
+    assert!(entered.is_ok());
+    assert_eq!(transition.unwrap().to, BreakerState::HalfOpen);
+    // only a single probe goes through
+    assert!(breaker.enter().0.is_err());
+    assert_eq!(breaker.record(true).unwrap().to, BreakerState::Closed);
+    assert!(breaker.enter().0.is_ok());
+}
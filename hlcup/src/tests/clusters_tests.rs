@@ -0,0 +1,29 @@
+use crate::http::dto::Area;
+use crate::models::clusters::HitIndex;
+
+#[test]
+fn test_hit_index_neighbours() {
+    let area = |pos_x, pos_y, size| Area {
+        pos_x,
+        pos_y,
+        size_x: size,
+        size_y: size,
+    };
+
+    let mut hits = HitIndex::new(true);
+    hits.record(50, 50, 1);
+    hits.record(51, 52, 2);
+    hits.record(3000, 3000, 1);
+
+    assert_eq!(hits.neighbours(&area(60, 60, 1)), 3);
+    assert_eq!(hits.neighbours(&area(500, 500, 10)), 0);
+    assert_eq!(hits.neighbours(&area(0, 0, 3500)), 4);
+
+    assert!(hits.touches(&area(60, 60, 1), 50, 50));
+    assert!(!hits.touches(&area(500, 500, 10), 50, 50));
+
+    let mut disabled = HitIndex::new(false);
+    disabled.record(50, 50, 1);
+    assert_eq!(disabled.neighbours(&area(50, 50, 1)), 0);
+    assert!(!disabled.touches(&area(50, 50, 1), 50, 50));
+}
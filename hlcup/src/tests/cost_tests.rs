@@ -0,0 +1,34 @@
+use crate::models::cost::{size_bucket, ExploreCostModel};
+
+#[test]
+fn test_size_bucket() {
+    assert_eq!(size_bucket(1), 1);
+    assert_eq!(size_bucket(2), 2);
+    assert_eq!(size_bucket(3), 2);
+    assert_eq!(size_bucket(4), 3);
+    assert_eq!(size_bucket(3500 * 350), 21);
+}
+
+#[test]
+fn test_explore_cost_model() {
+    assert_eq!(ExploreCostModel::new(vec![]).cost_us(10), None);
+
+    // flat up to bucket 5, then grows
+    let model = ExploreCostModel::new(vec![
+        (1, 100., 50),
+        (3, 110., 50),
+        (5, 150., 50),
+        (7, 500., 50),
+        (9, 900., 1),
+    ]);
+    assert_eq!(model.cost_us(1), Some(100.));
+    assert_eq!(model.cost_us(4), Some(110.));
+    // bucket 4 sits between 3 and 5
+    assert_eq!(model.cost_us(8), Some(130.));
+    // bucket 9 has too few samples and is extrapolated
+    assert!(model.cost_us(256).unwrap() > 500.);
+    assert_eq!(model.knee_size(), Some(31));
+
+    let flat = ExploreCostModel::new(vec![(1, 100., 50), (3, 110., 50)]);
+    assert_eq!(flat.knee_size(), None);
+}
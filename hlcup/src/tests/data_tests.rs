@@ -1,48 +1,45 @@
 use crate::models::data::PendingDig;
 use crate::models::data::Treasure;
+use crate::models::priority::Prioritized;
 
 #[test]
 fn test_treasure_ord() {
     use std::collections::BinaryHeap;
 
     let mut hp = BinaryHeap::new();
-    hp.push(Treasure {
-        depth: 1,
-        treasure: String::new(),
-    });
-    hp.push(Treasure {
-        depth: 2,
-        treasure: String::new(),
-    });
+    hp.push(Prioritized::new(
+        Treasure {
+            depth: 1,
+            treasure: String::new(),
+            trace: 0,
+        },
+        Treasure::priority,
+    ));
+    hp.push(Prioritized::new(
+        Treasure {
+            depth: 2,
+            treasure: String::new(),
+            trace: 0,
+        },
+        Treasure::priority,
+    ));
 
-    assert_eq!(hp.pop().unwrap().depth, 2);
-    assert_eq!(hp.pop().unwrap().depth, 1);
+    assert_eq!(hp.pop().unwrap().item().depth, 2);
+    assert_eq!(hp.pop().unwrap().item().depth, 1);
 }
 
 #[test]
-fn test_dig_ord() {
-    use std::collections::BinaryHeap;
-    let mut hp = BinaryHeap::new();
-    hp.push(PendingDig {
+fn test_dig_next_level() {
+    let dig = PendingDig {
         x: 1,
         y: 0,
         depth: 2,
-        remaining: 11,
-    });
-    hp.push(PendingDig {
-        x: 3,
-        y: 0,
-        depth: 2,
-        remaining: 10,
-    });
-    hp.push(PendingDig {
-        x: 2,
-        y: 0,
-        depth: 1,
-        remaining: 10,
-    });
+        remaining: 2,
+        trace: 7,
+    };
 
-    assert_eq!(hp.pop().unwrap().x, 1);
-    assert_eq!(hp.pop().unwrap().x, 3);
-    assert_eq!(hp.pop().unwrap().x, 2);
-}
\ No newline at end of file
+    let next = dig.next_level(10, 1).unwrap();
+    assert_eq!((next.depth, next.remaining, next.trace), (3, 1, 7));
+    assert!(dig.next_level(10, 2).is_none());
+    assert!(dig.next_level(2, 0).is_none());
+}
@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+use crate::http::deadlines::Deadlines;
+use crate::http::scheduler::Endpoint;
+
+#[test]
+fn test_parse_deadlines() {
+    let default = Duration::from_millis(1000);
+    let deadlines = Deadlines::parse("explore=50, cash=3000", default).unwrap();
+
+    assert_eq!(deadlines.get(Endpoint::Explore), Some(Duration::from_millis(50)));
+    assert_eq!(deadlines.get(Endpoint::Cash), Some(Duration::from_millis(3000)));
+    assert_eq!(deadlines.get(Endpoint::Dig), Some(default));
+    // licenses are not given up on unless asked to
+    assert_eq!(deadlines.get(Endpoint::License), None);
+    assert_eq!(Deadlines::new(default).get(Endpoint::Cash), None);
+    assert!(Deadlines::parse("explore", default).is_none());
+    assert!(Deadlines::parse("explore=soon", default).is_none());
+}
@@ -0,0 +1,57 @@
+use crate::http::dto::{Area, Explore};
+use crate::models::density::DensityModel;
+
+fn explore(size_x: u64, size_y: u64, amount: u64) -> Explore {
+    Explore {
+        area: Area {
+            pos_x: 0,
+            pos_y: 0,
+            size_x,
+            size_y,
+        },
+        amount,
+    }
+}
+
+#[test]
+fn test_density_posterior() {
+    let mut model = DensityModel::new();
+    assert_eq!(model.rate(), 0.04);
+
+    model.observe(&explore(100, 100, 1000), u64::MAX);
+    assert!((model.rate() - 0.1).abs() < 0.001);
+
+    // unknown amounts are not evidence
+    model.observe(&explore(100, 100, u64::MAX), u64::MAX);
+    assert!((model.rate() - 0.1).abs() < 0.001);
+
+    // neither are parts of an area known to hold treasure, they are sampled around it
+    model.observe(&explore(1, 1, 1), 3);
+    assert!((model.rate() - 0.1).abs() < 0.001);
+
+    assert!((model.expected_amount(&explore(10, 10, u64::MAX)) - 10.).abs() < 0.1);
+    assert_eq!(model.expected_amount(&explore(10, 10, 3)), 3.);
+}
+
+#[test]
+fn test_cell_probability() {
+    let model = DensityModel::new();
+
+    assert_eq!(model.cell_probability(&explore(1, 1, 1)), 1.);
+    assert_eq!(model.cell_probability(&explore(2, 1, 1)), 0.5);
+    assert_eq!(model.cell_probability(&explore(2, 1, 2)), 0.75);
+    assert_eq!(model.cell_probability(&explore(4, 4, 0)), 0.);
+    assert!((model.cell_probability(&explore(4, 4, u64::MAX)) - 0.0392).abs() < 0.001);
+}
+
+#[test]
+fn test_cell_treasures() {
+    let mut model = DensityModel::new();
+
+    assert_eq!(model.cell_treasures(&explore(4, 4, 3)), 1);
+    assert_eq!(model.cell_treasures(&explore(2, 2, 9)), 3);
+    assert_eq!(model.cell_treasures(&explore(4, 4, u64::MAX)), 1);
+
+    model.observe(&explore(10, 10, 250), u64::MAX);
+    assert_eq!(model.cell_treasures(&explore(4, 4, u64::MAX)), 3);
+}
@@ -0,0 +1,32 @@
+use std::collections::BTreeMap;
+
+use crate::models::depth::DepthModel;
+
+#[test]
+fn test_depth_model_prior() {
+    let model = DepthModel::default();
+
+    assert_eq!(model.hazard(1, 10), 0.1);
+    assert_eq!(model.hazard(10, 10), 1.);
+    assert_eq!(model.hazard(11, 10), 0.);
+    assert_eq!(model.expected_coins(10, 1, 10), 1.);
+    assert_eq!(model.expected_coins(10, 0, 10), 0.);
+    // the more treasures are left in the cell the likelier one of them is at this depth
+    assert!((model.expected_coins(1, 1, 10) - 0.1).abs() < 1e-9);
+    assert!((model.expected_coins(1, 2, 10) - 0.19).abs() < 1e-9);
+    assert!(model.expected_coins(5, 3, 10) > model.expected_coins(5, 2, 10));
+}
+
+#[test]
+fn test_depth_model_measured() {
+    let found: BTreeMap<u8, (f64, f64)> = vec![(1, (1000., 10.)), (2, (1000., 500.))]
+        .into_iter()
+        .collect();
+    let model = DepthModel::new(&found, vec![(2, 4.)]);
+
+    assert!(model.hazard(1, 10) < 0.02);
+    assert!((model.hazard(2, 10) - 0.5).abs() < 0.01);
+    assert!(model.expected_coins(2, 1, 10) > 10. * model.expected_coins(1, 1, 10));
+    assert_eq!(model.coins(2), 4.);
+    assert_eq!(model.coins(3), 1.);
+}
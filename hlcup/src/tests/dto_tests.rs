@@ -1,5 +1,6 @@
 use crate::http::dto::Explore;
 use crate::http::dto::Area;
+use crate::models::priority::{area_tie, Prioritized, Score};
 
 #[test]
 fn test_area_divide() {
@@ -68,36 +69,68 @@ fn test_area_divide() {
 #[test]
 fn test_explore_ord() {
     use std::collections::BinaryHeap;
+
+    let density = |e: &Explore| {
+        Score::ratio(e.amount as u128, e.area.size() as u128).then(area_tie(&e.area))
+    };
     let mut hp = BinaryHeap::new();
-    hp.push(Explore {
-        area: Area {
-            pos_x: 0,
-            pos_y: 0,
-            size_x: 100,
-            size_y: 100,
+    hp.push(Prioritized::new(
+        Explore {
+            area: Area {
+                pos_x: 0,
+                pos_y: 0,
+                size_x: 100,
+                size_y: 100,
+            },
+            amount: 10,
         },
-        amount: 10,
-    });
-    hp.push(Explore {
-        area: Area {
-            pos_x: 0,
-            pos_y: 0,
-            size_x: 10,
-            size_y: 10,
+        density,
+    ));
+    hp.push(Prioritized::new(
+        Explore {
+            area: Area {
+                pos_x: 0,
+                pos_y: 0,
+                size_x: 10,
+                size_y: 10,
+            },
+            amount: 10,
         },
-        amount: 10,
-    });
-    hp.push(Explore {
-        area: Area {
-            pos_x: 0,
-            pos_y: 0,
-            size_x: 1,
-            size_y: 1,
+        density,
+    ));
+    hp.push(Prioritized::new(
+        Explore {
+            area: Area {
+                pos_x: 0,
+                pos_y: 0,
+                size_x: 1,
+                size_y: 1,
+            },
+            amount: 3,
         },
-        amount: 3,
-    });
+        density,
+    ));
+
+    assert_eq!(hp.pop().unwrap().item().area.size(), 1);
+    assert_eq!(hp.pop().unwrap().item().area.size(), 100);
+    assert_eq!(hp.pop().unwrap().item().area.size(), 10000);
+}
+
+#[test]
+fn test_area_split_levels() {
+    let a = Area {
+        pos_x: 0,
+        pos_y: 0,
+        size_x: 16,
+        size_y: 16,
+    };
+
+    let parts = a.clone().split_levels(2);
+    assert_eq!(parts.len(), 16);
+    assert!(parts.iter().all(|p| p.size() == 16));
+    assert_eq!(parts.iter().map(Area::size).sum::<u64>(), a.size());
 
-    assert_eq!(hp.pop().unwrap().area.size(), 1);
-    assert_eq!(hp.pop().unwrap().area.size(), 100);
-    assert_eq!(hp.pop().unwrap().area.size(), 10000);
-}
\ No newline at end of file
+    assert_eq!(a.clone().split_levels(1).len(), 4);
+    // single cells are not divided any further
+    assert_eq!(a.split_levels(5).len(), 256);
+}
@@ -0,0 +1,38 @@
+use reqwest::StatusCode;
+
+use crate::http::exporter::render;
+use crate::models::metrics::{Origin, Registry};
+
+#[test]
+fn test_render_prometheus_text() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Worker(0));
+    metrics.record_dig(2, 100, true, None);
+    metrics.record_dig(2, 300, false, None);
+    metrics.record_dig(2, 5, false, Some(StatusCode::SERVICE_UNAVAILABLE));
+    metrics.record_license(50, 1, 5, None);
+    metrics.record_balance(7, 2, 0);
+
+    let text = render(&registry.snapshot());
+    assert!(text.contains("# TYPE hlcup_requests_total counter\n"));
+    assert!(text.contains("hlcup_requests_total{endpoint=\"dig\"} 3\n"));
+    assert!(text.contains("hlcup_requests_total{endpoint=\"explore\"} 0\n"));
+    assert!(text.contains("hlcup_request_errors_total{endpoint=\"dig\",status=\"503\"} 1\n"));
+    // every bound is written, empty or not
+    let buckets = text
+        .lines()
+        .filter(|l| l.starts_with("hlcup_request_duration_microseconds_bucket{endpoint=\"dig\",key=\"2\""))
+        .collect::<Vec<&str>>();
+    assert_eq!(buckets.len(), 17);
+    assert_eq!(buckets[0], "hlcup_request_duration_microseconds_bucket{endpoint=\"dig\",key=\"2\",le=\"50\"} 1");
+    assert!(text.contains("hlcup_request_duration_microseconds_bucket{endpoint=\"dig\",key=\"2\",le=\"250\"} 2\n"));
+    assert!(text.contains("hlcup_request_duration_microseconds_bucket{endpoint=\"dig\",key=\"2\",le=\"5000000\"} 3\n"));
+    assert!(text.contains("hlcup_request_duration_microseconds_bucket{endpoint=\"dig\",key=\"2\",le=\"+Inf\"} 3\n"));
+    assert!(text.contains("hlcup_request_duration_microseconds_sum{endpoint=\"dig\",key=\"2\"} 405\n"));
+    assert!(text.contains("hlcup_dig_found_rate{depth=\"2\"} 0.3333333333333333\n"));
+    assert!(text.contains("hlcup_licenses_total{coins=\"1\"} 1\n"));
+    assert!(text.contains("hlcup_origin_requests_total{origin=\"worker-0\"} 4\n"));
+    assert!(text.contains("hlcup_origin_found_total{origin=\"worker-0\"} 1\n"));
+    assert!(text.contains("hlcup_coins 7\n"));
+    assert!(text.contains("hlcup_active_licenses 2\n"));
+}
@@ -0,0 +1,57 @@
+use crate::constants::MAX_MAP_AREAS;
+use crate::http::dto::Area;
+use crate::models::heatmap::FieldMap;
+use crate::models::ledger::{DigOutcome, DigRecord};
+use crate::models::metrics::Origin;
+
+fn area(pos_x: u64, pos_y: u64, size_x: u64, size_y: u64) -> Area {
+    Area { pos_x, pos_y, size_x, size_y }
+}
+
+fn dig(depth: u8, outcome: DigOutcome) -> DigRecord {
+    DigRecord { depth, license: 1, outcome }
+}
+
+#[test]
+fn test_explored_paints_smaller_areas_over_larger() {
+    let mut map = FieldMap::default();
+    map.record_area(area(0, 0, 1, 2), 2);
+    map.record_area(area(0, 0, 4, 2), 2);
+    map.record_area(area(2, 0, 2, 2), 0);
+
+    let (width, height) = map.bounds();
+    assert_eq!((width, height), (4, 2));
+    let pgm = map.explored(width, height).to_pgm();
+    let header = b"P5\n4 2\n255\n";
+    assert_eq!(&pgm[..header.len()], header);
+    assert_eq!(&pgm[header.len()..], &[255, 94, 40, 40, 255, 94, 40, 40]);
+}
+
+#[test]
+fn test_dug_and_found_per_cell() {
+    let mut map = FieldMap::default();
+    let history = vec![dig(1, DigOutcome::Found(0)), dig(2, DigOutcome::Found(2))];
+    map.cells.insert((0, 0), (Origin::Worker(0), history));
+    let history = vec![dig(1, DigOutcome::Found(1)), dig(2, DigOutcome::Failed)];
+    map.cells.insert((2, 0), (Origin::Worker(1), history));
+
+    let (width, height) = map.bounds();
+    let header = b"P5\n3 1\n255\n".len();
+    assert_eq!(&map.dug(width, height).to_pgm()[header..], &[255, 0, 148]);
+    assert_eq!(&map.found(width, height).to_pgm()[header..], &[255, 0, 148]);
+
+    let csv = map.cells_csv();
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(lines[0], "x,y,origin,depth,license,found");
+    assert_eq!(lines[1], "0,0,worker-0,1,1,0");
+    assert_eq!(lines[4], "2,0,worker-1,2,1,");
+}
+
+#[test]
+fn test_areas_are_bounded() {
+    let mut map = FieldMap::default();
+    for i in 0..MAX_MAP_AREAS as u64 + 10 {
+        map.record_area(area(i, 0, 1, 2), 1);
+    }
+    assert_eq!(map.areas.len(), MAX_MAP_AREAS);
+}
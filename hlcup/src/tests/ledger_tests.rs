@@ -0,0 +1,60 @@
+use crate::models::data::PendingDig;
+use crate::models::ledger::{DigLedger, DigOutcome, DigRecord};
+
+#[test]
+fn test_ledger_depths() {
+    let mut ledger = DigLedger::new();
+    let record = |depth, outcome| DigRecord {
+        depth,
+        license: 1,
+        outcome,
+    };
+    assert_eq!(ledger.next_depth(3, 4), 1);
+
+    ledger.record(3, 4, record(1, DigOutcome::Found(0)));
+    ledger.record(3, 4, record(2, DigOutcome::Failed));
+    // the failed level is retried, the one dug before is not
+    assert_eq!(ledger.next_depth(3, 4), 2);
+    let mut dig = PendingDig::new(3, 4, 1, 0);
+    assert!(ledger.align(&mut dig, 10));
+    assert_eq!(dig.depth, 2);
+
+    ledger.record(3, 4, record(2, DigOutcome::Found(1)));
+    assert_eq!(ledger.finish(3, 4).len(), 3);
+    // finished cells are forgotten
+    assert_eq!(ledger.next_depth(3, 4), 1);
+    assert!(ledger.finish(3, 4).is_empty());
+}
+
+#[test]
+fn test_ledger_ambiguous_failures() {
+    let mut ledger = DigLedger::new();
+    let record = |depth, outcome| DigRecord {
+        depth,
+        license: 1,
+        outcome,
+    };
+    ledger.record(1, 1, record(1, DigOutcome::Found(0)));
+    // timed out, the server may or may not have dug level 2
+    ledger.record(1, 1, record(2, DigOutcome::Unknown));
+    assert_eq!(ledger.next_depth(1, 1), 2);
+
+    // a rejection for anything but the depth says nothing about the level
+    ledger.record(1, 1, record(2, DigOutcome::Failed));
+    assert_eq!(ledger.next_depth(1, 1), 2);
+
+    // told the level is not the next one right after: it had been dug
+    ledger.record(1, 1, record(2, DigOutcome::WrongDepth));
+    assert_eq!(ledger.next_depth(1, 1), 3);
+
+    // a wrong depth alone is no evidence
+    ledger.record(1, 1, record(3, DigOutcome::WrongDepth));
+    assert_eq!(ledger.next_depth(1, 1), 3);
+
+    // nothing is left to dig past the bottom level
+    ledger.record(1, 1, record(3, DigOutcome::Unknown));
+    ledger.record(1, 1, record(3, DigOutcome::WrongDepth));
+    let mut dig = PendingDig::new(1, 1, 1, 0);
+    assert!(!ledger.align(&mut dig, 3));
+    assert_eq!(dig.depth, 4);
+}
@@ -0,0 +1,58 @@
+use futures::FutureExt;
+use reqwest::StatusCode;
+
+use crate::http::limiter::Limiter;
+
+#[test]
+fn test_overload_cuts_window() {
+    let limiter = Limiter::new(4);
+    // cut at most once per round trip of the window
+    for _ in 0..3 {
+        assert_eq!(limiter.record(1000, Some(StatusCode::TOO_MANY_REQUESTS)), None);
+    }
+    assert_eq!(limiter.record(1000, Some(StatusCode::TOO_MANY_REQUESTS)), Some(2));
+    assert_eq!(limiter.record(1000, None), None);
+    assert_eq!(limiter.record(1000, None), Some(1));
+}
+
+// records a response with every permit of the window taken
+fn record_full(limiter: &Limiter, duration_us: u64) -> Option<usize> {
+    let mut permits = vec![];
+    while let Some(permit) = limiter.acquire().now_or_never() {
+        permits.push(permit);
+    }
+    limiter.record(duration_us, Some(StatusCode::OK))
+}
+
+#[test]
+fn test_window_follows_latency() {
+    let limiter = Limiter::new(2);
+    let grown = (0..64).filter_map(|_| record_full(&limiter, 1000)).last();
+    assert!(grown.unwrap() > 2);
+
+    let cut = (0..64).filter_map(|_| record_full(&limiter, 10_000)).last();
+    assert!(cut.unwrap() < grown.unwrap());
+}
+
+#[test]
+fn test_window_grows_only_when_full() {
+    let limiter = Limiter::new(4);
+    let _permit = limiter.acquire().now_or_never().unwrap();
+    // the global cap keeps a single request in flight
+    for _ in 0..1000 {
+        assert_eq!(limiter.record(1000, Some(StatusCode::OK)), None);
+    }
+    // the same responses with the window full grow it
+    assert_eq!((0..8).filter_map(|_| record_full(&limiter, 1000)).next(), Some(5));
+}
+
+#[tokio::test]
+async fn test_acquire_within_window() {
+    let limiter = Limiter::new(1);
+    let first = limiter.acquire().await;
+    let second = limiter.acquire();
+    tokio::pin!(second);
+    assert!(futures::poll!(&mut second).is_pending());
+    drop(first);
+    second.await;
+}
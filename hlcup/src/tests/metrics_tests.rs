@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::http::breaker::BreakerState;
+use crate::http::scheduler::Endpoint;
+use crate::models::metrics::{Activity, Origin, Registry};
+
+#[test]
+fn test_percentiles_within_bucket_precision() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Worker(0));
+    for duration in 1..=1000 {
+        metrics.record_dig(1, duration, false, None);
+    }
+    let snapshot = registry.snapshot();
+    let histogram = &snapshot.dig.histograms[&1];
+    assert_eq!(histogram.entries(), 1000);
+    assert_eq!(histogram.minimum(), Some(1));
+    assert_eq!(histogram.maximum(), Some(1000));
+    assert_eq!(histogram.mean(), Some(500.5));
+
+    let p50 = histogram.percentile(50.).unwrap();
+    assert!((500..=563).contains(&p50), "p50 {}", p50);
+    let p99 = histogram.percentile(99.).unwrap();
+    assert!((990..=1000).contains(&p99), "p99 {}", p99);
+    assert_eq!(histogram.percentile(100.), Some(1000));
+}
+
+#[test]
+fn test_snapshot_merges_shards() {
+    let registry = Registry::new();
+    let first = registry.shard(Origin::Worker(0));
+    let second = registry.shard(Origin::Worker(1));
+    first.record_dig(2, 100, true, None);
+    second.record_dig(2, 300, false, None);
+    second.record_dig(3, 50, false, Some(StatusCode::SERVICE_UNAVAILABLE));
+    first.record_timeout(Endpoint::Cash);
+    second.record_timeout(Endpoint::Cash);
+    second.record_breaker(Endpoint::Dig, BreakerState::Open);
+    first.record_limit(Endpoint::Dig, 4);
+    second.record_limit(Endpoint::Dig, 3);
+
+    let snapshot = registry.snapshot();
+    assert_eq!(snapshot.dig.total(), 3);
+    assert_eq!(snapshot.dig.errors(), 1);
+    assert_eq!(snapshot.dig.statuses[&503], 1);
+    assert_eq!(snapshot.dig.histograms[&2].mean(), Some(200.));
+    assert_eq!(snapshot.dig_found_per_depth[&2], (2., 1.));
+    assert_eq!(snapshot.dig_found(), 1.);
+    assert_eq!(snapshot.timeouts[&Endpoint::Cash], 2);
+    assert_eq!(snapshot.breaker[&(Endpoint::Dig, BreakerState::Open)], 1);
+    // limits are shared, the latest one wins
+    assert_eq!(snapshot.limits[&Endpoint::Dig], 3);
+
+    let per_worker = &snapshot.origins[&Origin::Worker(1)];
+    assert_eq!((per_worker.requests, per_worker.errors, per_worker.timeouts), (2, 1, 1));
+    assert_eq!(per_worker.found_rate(), 0.);
+    assert_eq!(snapshot.origins[&Origin::Worker(0)].found_rate(), 1.);
+
+    assert_eq!(registry.endpoint(Endpoint::Dig).total(), 3);
+    assert_eq!(registry.endpoint(Endpoint::Explore).total(), 0);
+}
+
+#[test]
+fn test_failed_cash_left_out_of_coins() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Accounting);
+    metrics.record_cash(2, 6, 100, None);
+    metrics.record_cash(2, 0, 100, Some(StatusCode::SERVICE_UNAVAILABLE));
+
+    let snapshot = registry.snapshot();
+    assert_eq!(snapshot.cash.total(), 2);
+    assert_eq!(snapshot.cash.errors(), 1);
+    assert_eq!(snapshot.cash_at_depth.histograms[&2].mean(), Some(6.));
+}
+
+#[test]
+fn test_worker_times_per_shard() {
+    let registry = Registry::new();
+    let accounting = registry.shard(Origin::Accounting);
+    let worker = registry.shard(Origin::Worker(3));
+    accounting.record_time(Activity::Dig, Duration::from_millis(1));
+    worker.record_time(Activity::Explore, Duration::from_millis(2));
+    worker.record_time(Activity::Explore, Duration::from_millis(3));
+    worker.record_time(Activity::LicenseWait, Duration::from_millis(4));
+    std::thread::sleep(Duration::from_millis(20));
+
+    let snapshot = registry.snapshot();
+    let origins = snapshot.origins.keys().copied().collect::<Vec<Origin>>();
+    assert_eq!(origins, vec![Origin::Accounting, Origin::Worker(3)]);
+    assert_eq!(snapshot.origins[&Origin::Accounting].times.spent_us[&Activity::Dig], 1000);
+    let times = &snapshot.origins[&Origin::Worker(3)].times;
+    assert_eq!(times.spent_us[&Activity::Explore], 5000);
+    assert_eq!(times.spent_us[&Activity::LicenseWait], 4000);
+    assert_eq!(times.spent_us[&Activity::Idle], 0);
+    assert!(times.wall_us >= 20000);
+    assert_eq!(times.other_us(), times.wall_us - 9000);
+}
+
+#[test]
+fn test_error_samples_are_bounded() {
+    let registry = Registry::new();
+    let first = registry.shard(Origin::Worker(0));
+    let second = registry.shard(Origin::Worker(1));
+    first.record_error(Endpoint::License, "402 no more active licenses allowed");
+    second.record_error(Endpoint::License, "402 no more active licenses allowed");
+    first.record_error(Endpoint::License, "402 no more active licenses allowed");
+    for i in 0..20 {
+        first.record_error(Endpoint::Dig, &format!("error {}", i));
+    }
+    first.record_error(Endpoint::Explore, &"x".repeat(1000));
+
+    let snapshot = registry.snapshot();
+    assert_eq!(snapshot.license.samples["402 no more active licenses allowed"], 3);
+    assert_eq!(snapshot.dig.samples.len(), 8);
+    assert!(snapshot.dig.samples.contains_key("error 0"));
+    assert!(!snapshot.dig.samples.contains_key("error 8"));
+    // long bodies are cut
+    assert_eq!(snapshot.explore.samples.keys().next().unwrap().len(), 200);
+    assert!(snapshot.cash.samples.is_empty());
+}
+
+#[test]
+fn test_snapshot_since_keeps_later_counts_only() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Worker(0));
+    metrics.record_dig(1, 100, true, None);
+    metrics.record_dig(2, 100, false, Some(StatusCode::NOT_FOUND));
+    metrics.record_license(50, 3, 5, None);
+    let baseline = registry.snapshot();
+
+    metrics.record_dig(1, 100, false, None);
+    metrics.record_timeout(Endpoint::Dig);
+    metrics.record_balance(7, 1, 0);
+    let since = registry.snapshot().since(&baseline);
+
+    assert_eq!(since.dig.total(), 1);
+    assert_eq!(since.dig.errors(), 0);
+    assert_eq!(since.dig_found_per_depth.get(&1), Some(&(1., 0.)));
+    assert!(!since.dig_found_per_depth.contains_key(&2));
+    assert!(since.licenses_per_coins.is_empty());
+    assert_eq!(since.digs_allowed_total, 0);
+    assert_eq!(since.timeouts.get(&Endpoint::Dig), Some(&1));
+    assert_eq!(since.coins, 7);
+    let worker = &since.origins[&Origin::Worker(0)];
+    assert_eq!((worker.requests, worker.digs, worker.found), (1, 1, 0));
+}
@@ -1,2 +1,22 @@
 pub mod data_tests;
-pub mod dto_tests;
\ No newline at end of file
+pub mod dto_tests;
+pub mod strategy_tests;
+pub mod planner_tests;
+pub mod cost_tests;
+pub mod depth_tests;
+pub mod density_tests;
+pub mod clusters_tests;
+pub mod bandit_tests;
+pub mod priority_tests;
+pub mod task_tests;
+pub mod ledger_tests;
+pub mod scheduler_tests;
+pub mod limiter_tests;
+pub mod deadlines_tests;
+pub mod breaker_tests;
+pub mod transport_tests;
+pub mod metrics_tests;
+pub mod exporter_tests;
+pub mod report_tests;
+pub mod timeline_tests;
+pub mod heatmap_tests;
@@ -0,0 +1,46 @@
+use std::time::Instant;
+
+use crate::http::dto::{Area, Explore};
+use crate::models::metrics::{Origin, Registry};
+use crate::models::planner::Planner;
+
+#[test]
+fn test_planner_cost() {
+    let planner = Planner::new(Instant::now(), Registry::new());
+
+    let explore = |size, amount| Explore {
+        area: Area {
+            pos_x: 0,
+            pos_y: 0,
+            size_x: size,
+            size_y: size,
+        },
+        amount,
+    };
+
+    // single cell is only dug
+    assert_eq!(planner.cost_us(&explore(1, 1), 9), 3. * planner.dig_us());
+    // empty areas cost nothing
+    assert_eq!(planner.cost_us(&explore(16, 0), 9), 0.);
+    assert!(planner.cost_us(&explore(16, 2), 9) > planner.cost_us(&explore(16, 1), 9));
+    assert!(planner.is_managable(&explore(16, 2), 9));
+    assert!(!planner.is_managable(&explore(3500, u64::MAX), 9));
+    assert!(planner.dig_capacity() > 0);
+
+    // a lone treasure takes 3 explores per quadtree level while all sizes cost the same
+    let cost = planner.cost_us(&explore(16, 1), 9) - 3. * planner.dig_us();
+    assert_eq!(cost, 4. * 3. * planner.explore_us(1));
+}
+
+#[test]
+fn test_planner_reads_registry() {
+    let registry = Registry::new();
+    let mut planner = Planner::new(Instant::now(), registry.clone());
+    let metrics = registry.shard(Origin::Worker(0));
+    metrics.record_dig(1, 300, true, None);
+    metrics.record_dig(2, 500, false, None);
+
+    planner.refresh();
+    assert_eq!(planner.dig_us(), 400.);
+    assert_eq!(planner.digs_per_treasure(9), 1.);
+}
@@ -0,0 +1,49 @@
+use std::cmp::Ordering;
+
+use crate::http::dto::Area;
+use crate::models::priority::{area_tie, cell_tie, Prioritized, Score};
+
+#[test]
+fn test_score_exact() {
+    assert!(Score::ratio(1, 3) > Score::ratio(333_333, 1_000_000));
+    assert!(Score::ratio(1, 10_000) > Score::ratio(0, 1));
+    assert!(Score::ratio(1, 10_000) < Score::ratio(1, 9_999));
+    assert_eq!(Score::ratio(1, 2), Score::ratio(2, 4));
+    assert_eq!(Score::ratio(1, 2).cmp(&Score::ratio(2, 4)), Ordering::Equal);
+    assert!(Score::ratio(7, 0) > Score::ratio(6, 1));
+    assert!(Score::fixed(0.5) == Score::ratio(1, 2));
+}
+
+#[test]
+fn test_score_no_overflow() {
+    let max = u64::MAX as u128;
+    assert!(Score::ratio(max * 1500, 4) > Score::ratio(max * 1499, 4));
+    assert!(Score::ratio(u128::MAX, u128::MAX - 1) < Score::ratio(u128::MAX - 1, u128::MAX - 2));
+    assert!(Score::ratio(u128::MAX - 1, u128::MAX) < Score::ratio(u128::MAX, u128::MAX));
+}
+
+#[test]
+fn test_score_tie_break() {
+    let area = |pos_x, size_x| Area {
+        pos_x,
+        pos_y: 0,
+        size_x,
+        size_y: 1,
+    };
+
+    // same density, the smaller area wins
+    let small = Score::ratio(1, 2).then(area_tie(&area(0, 2)));
+    let large = Score::ratio(2, 4).then(area_tie(&area(0, 4)));
+    assert!(small > large);
+    assert_ne!(small, large);
+
+    // same size, the one closer to the origin wins
+    assert!(area_tie(&area(0, 2)) > area_tie(&area(2, 2)));
+    assert!(cell_tie(0, 5) > cell_tie(1, 0));
+
+    let a = Prioritized::new("a", |_| small);
+    let b = Prioritized::new("b", |_| large);
+    assert!(a > b);
+    assert_eq!(a.score(), small);
+    assert_eq!(b.into_inner(), "b");
+}
@@ -0,0 +1,35 @@
+use reqwest::StatusCode;
+
+use crate::http::scheduler::Endpoint;
+use crate::models::metrics::{Origin, Registry};
+use crate::models::report::{CellsReport, Report, REPORT_SCHEMA};
+
+#[test]
+fn test_report_json_schema() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Worker(0));
+    metrics.record_dig(3, 120, true, None);
+    metrics.record_dig(3, 80, false, Some(StatusCode::INTERNAL_SERVER_ERROR));
+    metrics.record_queue(Endpoint::Dig, 10);
+    metrics.record_balance(4, 1, 0);
+    let cells = CellsReport {
+        dug: 1,
+        digs: 2,
+        failed: 1,
+    };
+
+    let report = Report::new(&registry.snapshot(), cells, 42);
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["schema"], REPORT_SCHEMA);
+    assert_eq!(json["timestamp_ms"], 42);
+    assert_eq!(json["endpoints"]["dig"]["requests"], 2);
+    assert_eq!(json["endpoints"]["dig"]["errors"], 1);
+    assert_eq!(json["endpoints"]["dig"]["statuses"]["500"], 1);
+    assert_eq!(json["endpoints"]["dig"]["latency_us"]["3"]["max"], 120);
+    assert_eq!(json["endpoints"]["explore"]["requests"], 0);
+    assert_eq!(json["queue_delay_us"]["dig"]["count"], 1);
+    assert_eq!(json["dig_depths"]["3"]["found_rate"], 0.5);
+    assert_eq!(json["breaker"]["cash"]["open"], 0);
+    assert_eq!(json["cells"]["failed"], 1);
+    assert_eq!(json["coins"], 4);
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use crate::http::scheduler::{parse_priorities, Endpoint, Scheduler};
+
+#[test]
+fn test_parse_priorities() {
+    assert_eq!(
+        parse_priorities("explore, dig"),
+        Some(vec![
+            Endpoint::Explore,
+            Endpoint::Dig,
+            Endpoint::Cash,
+            Endpoint::License
+        ])
+    );
+    assert_eq!(parse_priorities("explore,unknown"), None);
+}
+
+#[tokio::test]
+async fn test_waiters_served_by_priority() {
+    let scheduler = Scheduler::new(1, Endpoint::ALL.to_vec());
+    let busy = scheduler.acquire(Endpoint::Explore).await;
+
+    let (tx, mut rx) = mpsc::channel(4);
+    for endpoint in [Endpoint::Explore, Endpoint::Dig, Endpoint::Cash] {
+        let (scheduler, tx) = (scheduler.clone(), tx.clone());
+        tokio::spawn(async move {
+            let _permit = scheduler.acquire(endpoint).await;
+            tx.send(endpoint).await.unwrap();
+        });
+        // waiters queue up in this order
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    drop(busy);
+
+    let mut served = vec![];
+    for _ in 0..3 {
+        served.push(rx.recv().await.unwrap());
+    }
+    assert_eq!(served, vec![Endpoint::Cash, Endpoint::Dig, Endpoint::Explore]);
+}
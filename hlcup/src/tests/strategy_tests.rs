@@ -0,0 +1,165 @@
+use std::time::Instant;
+
+use crate::http::dto::{Area, Explore};
+use crate::models::bandit::Bandit;
+use crate::models::clusters::HitIndex;
+use crate::models::cost::ExploreCostModel;
+use crate::models::data::PendingDig;
+use crate::models::metrics::{Origin, Registry};
+use crate::models::priority::{Prioritized, Score};
+use crate::strategy::{self, Knowledge};
+use crate::Rules;
+
+fn knowledge() -> Knowledge {
+    Knowledge::new(Instant::now(), Registry::new(), &Rules::new(1, true), vec![])
+}
+
+fn explore(pos_x: u64, size_x: u64, size_y: u64, amount: u64) -> Explore {
+    Explore {
+        area: Area {
+            pos_x,
+            pos_y: 100,
+            size_x,
+            size_y,
+        },
+        amount,
+    }
+}
+
+#[test]
+fn test_from_name() {
+    assert_eq!(strategy::from_name(strategy::DEFAULT).unwrap().name(), "density");
+    assert_eq!(strategy::from_name("bandit").unwrap().name(), "bandit");
+    assert!(strategy::from_name("unknown").is_none());
+}
+
+#[test]
+fn test_explore_score_ord() {
+    use std::collections::BinaryHeap;
+
+    let strategy = strategy::from_name("density").unwrap();
+    let knowledge = knowledge();
+    let mut hp = BinaryHeap::new();
+    for (size, amount) in [(10, 10), (1, 3), (100, 10)] {
+        let explore = explore(0, size, size, amount);
+        hp.push(Prioritized::new(explore, |e| strategy.explore_score(e, &knowledge.context(10))));
+    }
+
+    assert_eq!(hp.pop().unwrap().item().area.size(), 1);
+    assert_eq!(hp.pop().unwrap().item().area.size(), 100);
+    assert_eq!(hp.pop().unwrap().item().area.size(), 10000);
+}
+
+#[test]
+fn test_blind_dig() {
+    let strategy = strategy::from_name("density").unwrap();
+    let knowledge = knowledge();
+
+    // every cell holds treasure for sure, exploring would be a waste
+    assert!(strategy.blind_dig(&explore(0, 2, 1, 10), &knowledge.context(10)));
+    assert!(!strategy.blind_dig(&explore(0, 16, 1, 1), &knowledge.context(10)));
+    assert!(!strategy.blind_dig(&explore(0, 1, 1, 1), &knowledge.context(10)));
+}
+
+#[test]
+fn test_observed_density_decides_unknown_areas() {
+    let strategy = strategy::from_name("density").unwrap();
+    let mut knowledge = knowledge();
+    let unknown = explore(0, 4, 4, u64::MAX);
+    let known = explore(10, 4, 4, 1);
+
+    assert!(!strategy.blind_dig(&unknown, &knowledge.context(10)));
+    let ctx = knowledge.context(10);
+    assert!(strategy.explore_score(&unknown, &ctx) < strategy.explore_score(&known, &ctx));
+
+    // two treasures per cell seen so far
+    knowledge.density.observe(&explore(1000, 100, 100, 20000), u64::MAX);
+    assert!(strategy.blind_dig(&unknown, &knowledge.context(10)));
+    let ctx = knowledge.context(10);
+    assert!(strategy.explore_score(&unknown, &ctx) > strategy.explore_score(&known, &ctx));
+}
+
+#[test]
+fn test_split_by_total_cost() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Worker(0));
+    for _ in 0..10 {
+        metrics.record_explore(1, 100, None);
+        metrics.record_explore(4, 1000, None);
+        metrics.record_explore(64, 5000, None);
+    }
+    // the curve bends right after single cells
+    assert_eq!(ExploreCostModel::new(registry.snapshot().explore.means()).knee_size(), Some(1));
+
+    let strategy = strategy::from_name("density").unwrap();
+    let mut knowledge = Knowledge::new(Instant::now(), registry, &Rules::new(1, true), vec![]);
+    knowledge.planner.refresh();
+    let ctx = knowledge.context(10);
+
+    // a lone treasure is still narrowed down quarter by quarter
+    assert_eq!(strategy.split(&explore(0, 1024, 1024, 1), &ctx).len(), 4);
+    // a cell per treasure is cheapest to explore cell by cell
+    let parts = strategy.split(&explore(0, 8, 8, 64), &ctx);
+    assert_eq!(parts.len(), 64);
+    assert!(parts.iter().all(|p| p.size() == 1));
+}
+
+#[test]
+fn test_cluster_boost() {
+    let strategy = strategy::from_name("density").unwrap();
+    let mut knowledge = knowledge();
+    knowledge.hits.record(2000, 100, 2);
+
+    let near = strategy.explore_score(&explore(2004, 4, 4, 1), &knowledge.context(10));
+    let far = strategy.explore_score(&explore(104, 4, 4, 1), &knowledge.context(10));
+    assert!(near > far);
+
+    // without the boost only the tie-break towards the origin is left
+    knowledge.hits = HitIndex::new(false);
+    let near = strategy.explore_score(&explore(2004, 4, 4, 1), &knowledge.context(10));
+    assert!(near < far);
+}
+
+#[test]
+fn test_pick_region() {
+    let density = strategy::from_name("density").unwrap();
+    let bandit = strategy::from_name("bandit").unwrap();
+    let mut knowledge = knowledge();
+    knowledge.bandit = Bandit::new(vec![
+        Area { pos_x: 0, pos_y: 0, size_x: 10, size_y: 10 },
+        Area { pos_x: 10, pos_y: 0, size_x: 10, size_y: 10 },
+    ]);
+    let tops = [(0, Score::ratio(5, 1)), (1, Score::ratio(10, 1))];
+
+    assert_eq!(density.pick_region(&tops, &knowledge.context(10)), Some(1));
+    assert_eq!(density.pick_region(&[], &knowledge.context(10)), None);
+
+    // region 1 was tried and brought nothing, region 0 never was
+    knowledge.bandit.record_request(1);
+    assert_eq!(bandit.pick_region(&tops, &knowledge.context(10)), Some(0));
+}
+
+#[test]
+fn test_dig_score_ord() {
+    use std::collections::BinaryHeap;
+
+    let strategy = strategy::from_name("density").unwrap();
+    let knowledge = knowledge();
+    let mut hp = BinaryHeap::new();
+    for (x, depth, remaining) in [(1, 2, 1), (2, 9, 1), (3, 2, 3)] {
+        let dig = PendingDig {
+            x,
+            y: 0,
+            depth,
+            remaining,
+            trace: 0,
+        };
+        hp.push(Prioritized::new(dig, |d| strategy.dig_score(d, &knowledge.context(10))));
+    }
+
+    // the prior puts a lone treasure at the bottom levels more likely,
+    // more treasures left in a cell make it likelier one of them is at the next level
+    assert_eq!(hp.pop().unwrap().item().x, 2);
+    assert_eq!(hp.pop().unwrap().item().x, 3);
+    assert_eq!(hp.pop().unwrap().item().x, 1);
+}
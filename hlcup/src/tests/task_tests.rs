@@ -0,0 +1,67 @@
+use std::collections::BinaryHeap;
+
+use crate::constants::MAX_ATTEMPTS;
+use crate::http::dto::{Area, Explore};
+use crate::models::priority::{Prioritized, Score};
+use crate::models::task::{Job, Narrowing};
+
+fn area(pos_x: u64) -> Area {
+    Area {
+        pos_x,
+        pos_y: 0,
+        size_x: 1,
+        size_y: 1,
+    }
+}
+
+#[test]
+fn test_suspend_keeps_unexplored_parts() {
+    let explore = Explore {
+        area: Area {
+            pos_x: 0,
+            pos_y: 0,
+            size_x: 4,
+            size_y: 1,
+        },
+        amount: 3,
+    };
+    let mut job = Job::new(Narrowing::new(explore));
+    assert_eq!(job.attempts, 0);
+    let trace = job.task.trace;
+
+    // the first part came back with a treasure, the second one failed
+    job.task.suspend(vec![area(0), area(1), area(2)], 1, Some(area(3)), 1);
+    let job = job.retry().unwrap();
+
+    assert_eq!(job.attempts, 1);
+    // retries keep the area's trace id
+    assert_eq!(job.task.trace, trace);
+    assert_ne!(Narrowing::new(Explore { area: area(0), amount: 1 }).trace, trace);
+    assert_eq!(job.task.found, 1);
+    assert_eq!(job.task.remaining, Some(vec![area(1), area(2), area(3)]));
+}
+
+#[test]
+fn test_failing_job_gives_way_and_is_dropped() {
+    // the same loop the worker runs: pop, fail, retry with the attempt's priority
+    let push = |heap: &mut BinaryHeap<Prioritized<Job<&'static str>>>, job: Job<&'static str>, score: u128| {
+        heap.push(Prioritized::new(job, |j| j.priority(Score::ratio(score, 1))))
+    };
+    let mut heap = BinaryHeap::new();
+    push(&mut heap, Job::new("failing"), 10);
+    push(&mut heap, Job::new("other"), 4);
+
+    let mut popped = vec![];
+    while let Some(job) = heap.pop().map(Prioritized::into_inner) {
+        popped.push(job.task);
+        if job.task == "failing" {
+            if let Some(job) = job.retry() {
+                push(&mut heap, job, 10);
+            }
+        }
+    }
+
+    // 10 / 3 falls below 4 on the third attempt
+    assert_eq!(popped[..3], ["failing", "failing", "other"]);
+    assert_eq!(popped.iter().filter(|t| **t == "failing").count(), MAX_ATTEMPTS as usize);
+}
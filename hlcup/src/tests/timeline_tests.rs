@@ -0,0 +1,45 @@
+use std::time::Instant;
+
+use crate::models::metrics::{Origin, Registry};
+use crate::models::timeline::Timeline;
+
+#[test]
+fn test_windows_hold_differences() {
+    let registry = Registry::new();
+    let metrics = registry.shard(Origin::Worker(0));
+    let mut timeline = Timeline::new(Instant::now());
+    metrics.record_dig(1, 5000, false, None);
+    timeline.sample_at(0, registry.snapshot());
+
+    metrics.record_dig(1, 100, true, None);
+    metrics.record_dig(1, 100, false, None);
+    metrics.record_cash(1, 6, 200, None);
+    metrics.record_license(50, 3, 5, None);
+    metrics.record_balance(3, 1, 2);
+    timeline.sample_at(1000, registry.snapshot());
+    timeline.sample_at(2000, registry.snapshot());
+
+    let json = serde_json::from_slice::<serde_json::Value>(&timeline.to_json().unwrap()).unwrap();
+    let windows = json.as_array().unwrap();
+    assert_eq!(windows.len(), 2);
+    let first = &windows[0];
+    assert_eq!(first["start_ms"], 0);
+    assert_eq!(first["duration_ms"], 1000);
+    assert_eq!(first["endpoints"]["dig"]["requests"], 2);
+    // the slow dig before the window is left out
+    assert!(first["endpoints"]["dig"]["p99_us"].as_u64().unwrap() < 200);
+    assert_eq!(first["treasures_found"], 1);
+    assert_eq!(first["coins_earned"], 6);
+    assert_eq!(first["coins_spent"], 3);
+    assert_eq!(first["digs_allowed"], 5);
+    assert_eq!(first["treasures"], 2);
+    assert_eq!(windows[1]["endpoints"]["dig"]["requests"], 0);
+    assert_eq!(windows[1]["coins"], 3);
+
+    let csv = timeline.to_csv();
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("start_ms,duration_ms,cash_requests,"));
+    assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+    assert!(lines[1].ends_with(",1,6,3,5,3,2,1"));
+}
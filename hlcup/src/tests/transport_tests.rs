@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::http::dto::Area;
+use crate::http::scheduler::Endpoint;
+use crate::http::transport::Transport;
+
+// reads one request and returns its body, `None` once the connection is closed
+async fn read_request(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Option<String> {
+    let mut chunk = [0u8; 1024];
+    loop {
+        let text = String::from_utf8_lossy(buf).to_string();
+        if let Some(head_end) = text.find("\r\n\r\n") {
+            let length = text[..head_end]
+                .lines()
+                .find_map(|l| {
+                    let l = l.to_lowercase();
+                    l.strip_prefix("content-length:").map(|v| v.trim().parse::<usize>())
+                })
+                .and_then(|v| v.ok())
+                .unwrap_or(0);
+            let end = head_end + 4 + length;
+            if buf.len() >= end {
+                let body = String::from_utf8_lossy(&buf[head_end + 4..end]).to_string();
+                buf.drain(..end);
+                return Some(body);
+            }
+        }
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    }
+}
+
+#[tokio::test]
+async fn test_transport_keeps_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let authority = listener.local_addr().unwrap().to_string();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![];
+        let mut served = 0;
+        while let Some(body) = read_request(&mut stream, &mut buf).await {
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            served += 1;
+        }
+        served
+    });
+
+    let transport = Transport::new(&authority).detached();
+    let area = Area {
+        pos_x: 1,
+        pos_y: 2,
+        size_x: 3,
+        size_y: 4,
+    };
+    for _ in 0..2 {
+        let (status, echoed) = transport
+            .post(Endpoint::Explore, &area, Some(Duration::from_secs(1)), |raw| {
+                (raw.status, serde_json::from_slice::<Area>(raw.body).unwrap())
+            })
+            .await
+            .unwrap();
+        assert_eq!(status, reqwest::StatusCode::OK);
+        assert_eq!(echoed, area);
+    }
+    drop(transport);
+
+    // both requests went over the single accepted connection
+    assert_eq!(server.await.unwrap(), 2);
+}